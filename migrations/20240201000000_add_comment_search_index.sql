@@ -0,0 +1,2 @@
+-- Full-text index over comment bodies so ticket search can match conversation history
+CREATE INDEX idx_comments_search ON comments USING gin(to_tsvector('english', content));
@@ -0,0 +1,14 @@
+-- Weighted search document for knowledge base articles: title > tags > content
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE knowledge_base ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') ||
+    setweight(jsonb_to_tsvector('english', tags, '["string"]'), 'B') ||
+    setweight(to_tsvector('english', content), 'C')
+) STORED;
+
+DROP INDEX idx_knowledge_base_search;
+CREATE INDEX idx_knowledge_base_search ON knowledge_base USING gin(search_vector);
+
+-- Typo-tolerant fallback when the full-text query finds nothing
+CREATE INDEX idx_knowledge_base_title_trgm ON knowledge_base USING gin(title gin_trgm_ops);
@@ -0,0 +1,24 @@
+-- Saved ticket views (named TicketQuery filter sets)
+CREATE TABLE saved_views (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    filters JSONB NOT NULL DEFAULT '{}',
+    sort VARCHAR(50),
+    columns JSONB NOT NULL DEFAULT '[]',
+    is_shared BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- When each user last opened a view, used for "new since last visit" counts
+CREATE TABLE saved_view_visits (
+    view_id UUID NOT NULL REFERENCES saved_views(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_viewed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (view_id, user_id)
+);
+
+CREATE INDEX idx_saved_views_owner_id ON saved_views(owner_id);
+CREATE INDEX idx_saved_views_is_shared ON saved_views(is_shared) WHERE is_shared;
+CREATE INDEX idx_tickets_updated_at ON tickets(updated_at);
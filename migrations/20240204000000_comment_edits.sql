@@ -0,0 +1,15 @@
+-- Comment editing and soft deletion
+ALTER TABLE comments ADD COLUMN edited_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE comments ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE comments ADD COLUMN deleted_by UUID REFERENCES users(id);
+
+-- Prior versions of a comment's content, one row per edit
+CREATE TABLE comment_revisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    edited_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_comment_revisions_comment_id ON comment_revisions(comment_id);
@@ -0,0 +1,13 @@
+-- Staff members @mentioned in internal notes, doubling as each user's mention inbox
+CREATE TABLE comment_mentions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    ticket_id UUID NOT NULL REFERENCES tickets(id) ON DELETE CASCADE,
+    mentioned_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    mentioned_by UUID NOT NULL REFERENCES users(id),
+    read_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (comment_id, mentioned_user_id)
+);
+
+CREATE INDEX idx_comment_mentions_inbox ON comment_mentions(mentioned_user_id, created_at DESC);
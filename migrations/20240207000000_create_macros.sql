@@ -0,0 +1,28 @@
+-- Free-form ticket labels, set directly or by macros
+ALTER TABLE tickets ADD COLUMN tags JSONB NOT NULL DEFAULT '[]';
+
+-- Canned responses: an optional comment template plus ticket field changes
+CREATE TABLE macros (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    body TEXT,
+    is_internal BOOLEAN NOT NULL DEFAULT false,
+    actions JSONB NOT NULL DEFAULT '{}',
+    is_shared BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- One row per time a macro is applied, for usage reporting
+CREATE TABLE macro_applications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    macro_id UUID NOT NULL REFERENCES macros(id) ON DELETE CASCADE,
+    ticket_id UUID NOT NULL REFERENCES tickets(id) ON DELETE CASCADE,
+    applied_by UUID NOT NULL REFERENCES users(id),
+    applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_macros_owner_id ON macros(owner_id);
+CREATE INDEX idx_macros_is_shared ON macros(is_shared) WHERE is_shared;
+CREATE INDEX idx_macro_applications_macro_id ON macro_applications(macro_id, applied_at);
@@ -0,0 +1,5 @@
+-- Replies to a specific comment, and the Message-ID of comments that arrived by email
+ALTER TABLE comments ADD COLUMN parent_comment_id UUID REFERENCES comments(id) ON DELETE SET NULL;
+ALTER TABLE comments ADD COLUMN email_message_id TEXT UNIQUE;
+
+CREATE INDEX idx_comments_parent_comment_id ON comments(parent_comment_id);
@@ -0,0 +1,15 @@
+-- One satisfaction survey per resolution, answered through a signed link
+CREATE TABLE csat_surveys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id UUID NOT NULL REFERENCES tickets(id) ON DELETE CASCADE,
+    agent_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    customer_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating SMALLINT CHECK (rating BETWEEN 1 AND 5),
+    comment TEXT,
+    sent_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_csat_surveys_ticket_id ON csat_surveys(ticket_id);
+CREATE INDEX idx_csat_surveys_sent_at ON csat_surveys(sent_at);
+CREATE INDEX idx_csat_surveys_agent_id ON csat_surveys(agent_id, sent_at);
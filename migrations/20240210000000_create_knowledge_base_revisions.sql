@@ -0,0 +1,20 @@
+-- Every saved version of an article, numbered per article, for history, diffs and rollback
+ALTER TABLE knowledge_base ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE knowledge_base_revisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    title VARCHAR(500) NOT NULL,
+    content TEXT NOT NULL,
+    category VARCHAR(100) NOT NULL,
+    tags JSONB NOT NULL DEFAULT '[]',
+    edited_by UUID NOT NULL REFERENCES users(id),
+    restored_from INTEGER,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (article_id, revision)
+);
+
+-- Existing articles start their history at revision 1
+INSERT INTO knowledge_base_revisions (article_id, revision, title, content, category, tags, edited_by, created_at)
+SELECT id, 1, title, content, category, tags, created_by, updated_at FROM knowledge_base;
@@ -0,0 +1,14 @@
+-- Editorial workflow: draft -> in_review -> published -> archived, with optional scheduling
+ALTER TABLE knowledge_base ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'draft'
+    CHECK (status IN ('draft', 'in_review', 'published', 'archived'));
+ALTER TABLE knowledge_base ADD COLUMN reviewer_id UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE knowledge_base ADD COLUMN approved_by UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE knowledge_base ADD COLUMN approved_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE knowledge_base ADD COLUMN publish_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE knowledge_base ADD COLUMN unpublish_at TIMESTAMP WITH TIME ZONE;
+
+-- Articles written before the workflow existed were already live
+UPDATE knowledge_base SET status = 'published';
+
+CREATE INDEX idx_knowledge_base_status ON knowledge_base(status);
+CREATE INDEX idx_knowledge_base_reviewer_id ON knowledge_base(reviewer_id) WHERE status = 'in_review';
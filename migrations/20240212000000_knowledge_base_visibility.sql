@@ -0,0 +1,5 @@
+-- Who may read an article once it is live: anyone, signed-in customers, or staff only
+ALTER TABLE knowledge_base ADD COLUMN visibility VARCHAR(20) NOT NULL DEFAULT 'customers'
+    CHECK (visibility IN ('public', 'customers', 'internal'));
+
+CREATE INDEX idx_knowledge_base_public ON knowledge_base(updated_at) WHERE visibility = 'public' AND status = 'published';
@@ -0,0 +1,9 @@
+-- A query matching any of the words in `words`, used to suggest articles from long
+-- ticket text. Built from the lexemes themselves, each quoted as tsquery input, so it
+-- does not depend on how Postgres prints a tsquery. NULL when there are no words.
+CREATE FUNCTION knowledge_base_any_word_query(words TEXT) RETURNS tsquery
+    LANGUAGE sql IMMUTABLE STRICT PARALLEL SAFE
+    AS $$
+        SELECT string_agg('''' || replace(replace(lexeme, '\', '\\'), '''', '''''') || '''', ' | ')::tsquery
+        FROM unnest(tsvector_to_array(to_tsvector('english', words))) AS lexeme
+    $$;
@@ -0,0 +1,12 @@
+-- Knowledge base articles an agent used on a ticket, optionally sent in a comment
+CREATE TABLE ticket_articles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id UUID NOT NULL REFERENCES tickets(id) ON DELETE CASCADE,
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    linked_by UUID NOT NULL REFERENCES users(id),
+    comment_id UUID REFERENCES comments(id) ON DELETE SET NULL,
+    linked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (ticket_id, article_id)
+);
+
+CREATE INDEX idx_ticket_articles_article_id ON ticket_articles(article_id, linked_at);
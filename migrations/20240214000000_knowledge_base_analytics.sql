@@ -0,0 +1,36 @@
+-- Article views, counted once per viewer per day. `viewer` is `user:<id>` for
+-- signed-in users or `session:<id>` for anonymous visitors' help center sessions.
+CREATE TABLE knowledge_base_views (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    viewer TEXT NOT NULL,
+    viewed_on DATE NOT NULL DEFAULT CURRENT_DATE,
+    viewed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (article_id, viewer, viewed_on)
+);
+
+-- "Was this helpful?" answers, one per voter per article; voting again replaces it
+CREATE TABLE knowledge_base_votes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    voter TEXT NOT NULL,
+    helpful BOOLEAN NOT NULL,
+    feedback TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (article_id, voter)
+);
+
+-- Searches that found no articles, lowercased with whitespace collapsed
+CREATE TABLE knowledge_base_search_misses (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    query TEXT NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    searched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_knowledge_base_views_viewed_at ON knowledge_base_views(viewed_at);
+CREATE INDEX idx_knowledge_base_votes_article_id ON knowledge_base_votes(article_id);
+CREATE INDEX idx_knowledge_base_search_misses_searched_at ON knowledge_base_search_misses(searched_at);
@@ -0,0 +1,38 @@
+-- URL-friendly article identifiers, also used to match articles on import
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+ALTER TABLE knowledge_base ADD COLUMN slug VARCHAR(255);
+
+-- Backfills slugs the way `slugify` makes them: accents are transliterated, other
+-- characters become hyphens, and the result is cut to 245 characters to leave room for
+-- a numeric suffix. Later articles with a taken slug get the lowest free suffix.
+-- `unaccent` only covers Latin accents, so titles in other scripts get `article`
+-- slugs here where `slugify` would transliterate them; editors can rename those.
+DO $$
+DECLARE
+    article RECORD;
+    base TEXT;
+    candidate TEXT;
+    n INT;
+BEGIN
+    FOR article IN SELECT id, title FROM knowledge_base ORDER BY created_at, id LOOP
+        base := trim(BOTH '-' FROM regexp_replace(lower(unaccent(article.title)), '[^a-z0-9]+', '-', 'g'));
+        base := rtrim(left(base, 245), '-');
+        IF base = '' THEN
+            base := 'article';
+        END IF;
+
+        candidate := base;
+        n := 1;
+        WHILE EXISTS (SELECT 1 FROM knowledge_base WHERE slug = candidate) LOOP
+            n := n + 1;
+            candidate := base || '-' || n;
+        END LOOP;
+
+        UPDATE knowledge_base SET slug = candidate WHERE id = article.id;
+    END LOOP;
+END
+$$;
+
+ALTER TABLE knowledge_base ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE knowledge_base ADD CONSTRAINT knowledge_base_slug_key UNIQUE (slug);
@@ -0,0 +1,8 @@
+-- Earlier slugs of renamed articles, so old links keep working
+CREATE TABLE knowledge_base_slug_redirects (
+    slug VARCHAR(255) PRIMARY KEY,
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_knowledge_base_slug_redirects_article_id ON knowledge_base_slug_redirects(article_id);
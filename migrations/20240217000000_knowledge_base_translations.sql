@@ -0,0 +1,48 @@
+-- Articles are written in one language and may be translated into the others.
+-- Search uses each language's own text search configuration.
+CREATE FUNCTION knowledge_base_search_config(locale TEXT) RETURNS regconfig
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE
+    AS $$
+        SELECT CASE locale
+            WHEN 'de' THEN 'pg_catalog.german'
+            WHEN 'es' THEN 'pg_catalog.spanish'
+            ELSE 'pg_catalog.english'
+        END::regconfig
+    $$;
+
+ALTER TABLE knowledge_base ADD COLUMN locale VARCHAR(10) NOT NULL DEFAULT 'en';
+
+-- Dropping the column drops idx_knowledge_base_search with it
+ALTER TABLE knowledge_base DROP COLUMN search_vector;
+ALTER TABLE knowledge_base ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector(knowledge_base_search_config(locale), title), 'A') ||
+    setweight(jsonb_to_tsvector(knowledge_base_search_config(locale), tags, '["string"]'), 'B') ||
+    setweight(to_tsvector(knowledge_base_search_config(locale), content), 'C')
+) STORED;
+CREATE INDEX idx_knowledge_base_search ON knowledge_base USING gin(search_vector);
+
+CREATE TABLE knowledge_base_translations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    article_id UUID NOT NULL REFERENCES knowledge_base(id) ON DELETE CASCADE,
+    locale VARCHAR(10) NOT NULL,
+    title VARCHAR(500) NOT NULL,
+    content TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'published')),
+    -- Revision of the article the translation was made from
+    source_revision INTEGER NOT NULL,
+    -- The article's title or content has changed since source_revision
+    outdated BOOLEAN NOT NULL DEFAULT FALSE,
+    translated_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector(knowledge_base_search_config(locale), title), 'A') ||
+        setweight(to_tsvector(knowledge_base_search_config(locale), content), 'C')
+    ) STORED,
+    UNIQUE (article_id, locale)
+);
+
+CREATE INDEX idx_knowledge_base_translations_search ON knowledge_base_translations USING gin(search_vector);
+
+-- Lets the fuzzy title search match translated titles without a full scan
+CREATE INDEX idx_knowledge_base_translations_title_trgm ON knowledge_base_translations USING gin(title gin_trgm_ops);
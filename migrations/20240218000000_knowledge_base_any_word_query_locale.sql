@@ -0,0 +1,10 @@
+-- Suggestions match each article in its own language, so the any-word query now
+-- takes the text search configuration instead of always using English.
+DROP FUNCTION knowledge_base_any_word_query(TEXT);
+
+CREATE FUNCTION knowledge_base_any_word_query(config regconfig, words TEXT) RETURNS tsquery
+    LANGUAGE sql IMMUTABLE STRICT PARALLEL SAFE
+    AS $$
+        SELECT string_agg('''' || replace(replace(lexeme, '\', '\\'), '''', '''''') || '''', ' | ')::tsquery
+        FROM unnest(tsvector_to_array(to_tsvector(config, words))) AS lexeme
+    $$;
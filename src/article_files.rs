@@ -0,0 +1,337 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+use validator::Validate;
+
+use crate::validation::{self, MAX_BODY_LEN, MAX_CATEGORY_LEN, MAX_TITLE_LEN};
+
+/// Largest single file read from an archive or directory: a full article plus front matter.
+const MAX_FILE_BYTES: u64 = MAX_BODY_LEN + 64 * 1024;
+/// Largest total size of the files read from one archive once decompressed.
+const MAX_EXTRACTED_BYTES: u64 = 200 * 1024 * 1024;
+const FRONT_MATTER_FENCE: &str = "---";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArticleFileError {
+    #[error("{path}: {message}")]
+    Invalid { path: String, message: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// One article in the import and export format. In Markdown files everything but
+/// `content` is YAML front matter and `content` is the rest of the file.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ArticleFile {
+    /// Matches the file to an existing article. Defaults to the file name for Markdown
+    /// files and to one made from the title in JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::slug")]
+    pub slug: Option<String>,
+    #[validate(length(min = 1, max = "MAX_TITLE_LEN"), custom = "validation::not_blank")]
+    pub title: String,
+    #[validate(length(min = 1, max = "MAX_CATEGORY_LEN"), custom = "validation::not_blank")]
+    pub category: String,
+    #[serde(default)]
+    #[validate(custom = "validation::tags")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::article_visibility")]
+    pub visibility: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::article_status")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::locale")]
+    pub locale: Option<String>,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slug: Option<String>,
+    title: String,
+    category: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visibility: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+}
+
+/// An article and the file it was read from, for error messages.
+#[derive(Debug)]
+pub struct ArticleEntry {
+    pub path: String,
+    pub article: ArticleFile,
+}
+
+impl ArticleEntry {
+    /// Whether the article came from a Markdown file rather than JSON.
+    pub fn is_markdown(&self) -> bool {
+        is_markdown(&self.path)
+    }
+}
+
+/// Reads an uploaded `.md`, `.json`, `.tar`, `.tar.gz` or `.tgz` file.
+pub fn read_file(name: &str, data: &[u8]) -> Result<Vec<ArticleEntry>, ArticleFileError> {
+    let lower = name.to_lowercase();
+
+    if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        read_tar(name, GzDecoder::new(data))
+    } else if lower.ends_with(".tar") {
+        read_tar(name, data)
+    } else if is_markdown(&lower) {
+        Ok(vec![parse_markdown(name, data)?])
+    } else if lower.ends_with(".json") {
+        parse_json(name, data)
+    } else {
+        Err(invalid(name, "expected a .md, .json, .tar, .tar.gz or .tgz file"))
+    }
+}
+
+/// Reads a file as `read_file` does, or every `.md` and `.json` file under a directory.
+pub fn read_path(path: &Path) -> Result<Vec<ArticleEntry>, ArticleFileError> {
+    if !path.is_dir() {
+        let name = path.to_string_lossy();
+        return read_file(&name, &fs::read(path)?);
+    }
+
+    let mut entries = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut children: Vec<_> = fs::read_dir(&dir)?.collect::<Result<_, _>>()?;
+        children.sort_by_key(|child| child.file_name());
+
+        for child in children {
+            let child_path = child.path();
+            let relative = child_path.strip_prefix(path).unwrap_or(&child_path).to_string_lossy().into_owned();
+            if is_hidden(&relative) {
+                continue;
+            }
+            if child.file_type()?.is_dir() {
+                dirs.push(child_path);
+            } else if is_article_file(&relative) {
+                if child.metadata()?.len() > MAX_FILE_BYTES {
+                    return Err(invalid(&relative, "the file is too large"));
+                }
+                entries.extend(read_file(&relative, &fs::read(&child_path)?)?);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// A gzipped tar of one `<slug>.md` file per article.
+pub fn write_archive(articles: &[ArticleFile]) -> io::Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for article in articles {
+        let markdown = to_markdown(article);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(markdown.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, file_name(article), markdown.as_bytes())?;
+    }
+
+    archive.into_inner()?.finish()
+}
+
+/// Writes one `<slug>.md` file per article into `dir`, creating it if needed.
+pub fn write_dir(dir: &Path, articles: &[ArticleFile]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for article in articles {
+        fs::write(dir.join(file_name(article)), to_markdown(article))?;
+    }
+    Ok(())
+}
+
+pub fn to_markdown(article: &ArticleFile) -> String {
+    let front_matter = FrontMatter {
+        slug: article.slug.clone(),
+        title: article.title.clone(),
+        category: article.category.clone(),
+        tags: article.tags.clone(),
+        visibility: article.visibility.clone(),
+        status: article.status.clone(),
+        locale: article.locale.clone(),
+    };
+    // Serialising plain strings and lists cannot fail.
+    let yaml = serde_yaml::to_string(&front_matter).unwrap_or_default();
+
+    format!("{FRONT_MATTER_FENCE}\n{yaml}{FRONT_MATTER_FENCE}\n\n{}", article.content)
+}
+
+/// Front matter between `---` lines at the top of the file, then the content after
+/// one blank line.
+fn parse_markdown(path: &str, data: &[u8]) -> Result<ArticleEntry, ArticleFileError> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid(path, "the file is not UTF-8"))?;
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+
+    let rest = text
+        .strip_prefix(FRONT_MATTER_FENCE)
+        .and_then(|rest| rest.strip_prefix('\n'))
+        .ok_or_else(|| invalid(path, "the file must start with `---` and YAML front matter"))?;
+    let (yaml, content) = match rest.split_once(&format!("\n{FRONT_MATTER_FENCE}\n")) {
+        Some((yaml, content)) => (yaml, content),
+        None => match rest.strip_suffix(&format!("\n{FRONT_MATTER_FENCE}")) {
+            Some(yaml) => (yaml, ""),
+            None => return Err(invalid(path, "the front matter is not closed with `---`")),
+        },
+    };
+
+    let front_matter: FrontMatter = serde_yaml::from_str(yaml).map_err(|e| invalid(path, e))?;
+
+    Ok(ArticleEntry {
+        path: path.to_string(),
+        article: ArticleFile {
+            slug: front_matter.slug,
+            title: front_matter.title,
+            category: front_matter.category,
+            tags: front_matter.tags,
+            visibility: front_matter.visibility,
+            status: front_matter.status,
+            locale: front_matter.locale,
+            content: content.strip_prefix('\n').unwrap_or(content).to_string(),
+        },
+    })
+}
+
+/// A JSON array of articles, as written by the JSON export.
+fn parse_json(path: &str, data: &[u8]) -> Result<Vec<ArticleEntry>, ArticleFileError> {
+    let articles: Vec<ArticleFile> = serde_json::from_slice(data).map_err(|e| invalid(path, e))?;
+
+    Ok(articles
+        .into_iter()
+        .enumerate()
+        .map(|(index, article)| ArticleEntry {
+            path: format!("{path}[{index}]"),
+            article,
+        })
+        .collect())
+}
+
+/// Every `.md` and `.json` file in the archive; other files and hidden paths are skipped.
+fn read_tar<R: Read>(name: &str, reader: R) -> Result<Vec<ArticleEntry>, ArticleFileError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut extracted = 0;
+
+    for file in archive.entries().map_err(|e| invalid(name, e))? {
+        let mut file = file.map_err(|e| invalid(name, e))?;
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = file.path().map_err(|e| invalid(name, e))?.to_string_lossy().into_owned();
+        let path = path.trim_start_matches("./").to_string();
+        if is_hidden(&path) || !is_article_file(&path) {
+            continue;
+        }
+
+        let size = file.size();
+        extracted += size;
+        if size > MAX_FILE_BYTES {
+            return Err(invalid(&path, "the file is too large"));
+        }
+        if extracted > MAX_EXTRACTED_BYTES {
+            return Err(invalid(name, "the archive is too large once extracted"));
+        }
+
+        let mut data = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut data).map_err(|e| invalid(&path, e))?;
+        let path = format!("{name}/{path}");
+        if is_markdown(&path) {
+            entries.push(parse_markdown(&path, &data)?);
+        } else {
+            entries.extend(parse_json(&path, &data)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+fn file_name(article: &ArticleFile) -> String {
+    format!("{}.md", article.slug.as_deref().unwrap_or("article"))
+}
+
+fn is_markdown(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+fn is_article_file(path: &str) -> bool {
+    is_markdown(path) || path.to_lowercase().ends_with(".json")
+}
+
+/// Dotfiles and anything under a dot directory such as `.git`.
+fn is_hidden(path: &str) -> bool {
+    path.split(['/', '\\']).any(|part| part.starts_with('.') && part != "." && part != "..")
+}
+
+fn invalid(path: &str, message: impl ToString) -> ArticleFileError {
+    ArticleFileError::Invalid {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article() -> ArticleFile {
+        ArticleFile {
+            slug: Some("reset-password".to_string()),
+            title: "Reset: your password".to_string(),
+            category: "Accounts".to_string(),
+            tags: vec!["login".to_string(), "password".to_string()],
+            visibility: Some("public".to_string()),
+            status: None,
+            locale: Some("de".to_string()),
+            content: "# Steps\n\n---\n\n1. Open *Settings*.\n".to_string(),
+        }
+    }
+
+    fn json(article: &ArticleFile) -> serde_json::Value {
+        serde_json::to_value(article).unwrap()
+    }
+
+    #[test]
+    fn markdown_round_trips() {
+        let article = article();
+        let entry = parse_markdown("reset-password.md", to_markdown(&article).as_bytes()).unwrap();
+        assert_eq!(json(&entry.article), json(&article));
+    }
+
+    #[test]
+    fn markdown_accepts_crlf_and_a_bom() {
+        let text = "\u{feff}---\r\ntitle: Hello\r\ncategory: General\r\n---\r\n\r\nBody\r\n";
+        let entry = parse_markdown("hello.md", text.as_bytes()).unwrap();
+        assert_eq!(entry.article.title, "Hello");
+        assert_eq!(entry.article.content, "Body\n");
+        assert!(parse_markdown("bad.md", b"title: Hello\n").is_err());
+    }
+
+    #[test]
+    fn archive_round_trips() {
+        let article = article();
+        let archive = write_archive(std::slice::from_ref(&article)).unwrap();
+        let entries = read_file("export.tar.gz", &archive).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "export.tar.gz/reset-password.md");
+        assert_eq!(json(&entries[0].article), json(&article));
+    }
+}
\ No newline at end of file
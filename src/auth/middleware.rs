@@ -1,48 +1,45 @@
 use axum::{
     extract::State,
-    http::{Request, header::AUTHORIZATION, StatusCode},
+    http::{Request, header::AUTHORIZATION},
     middleware::Next,
     response::Response,
 };
 use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait};
 use crate::auth::verify_token;
+use crate::error::AppError;
 use crate::models::{User, user};
 
 pub async fn auth_middleware<B>(
     State(db): State<DatabaseConnection>,
     mut request: Request<B>,
     next: Next<B>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let auth_header = request
         .headers()
         .get(AUTHORIZATION)
         .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_str| {
-            if auth_str.starts_with("Bearer ") {
-                Some(auth_str[7..].to_string())
-            } else {
-                None
-            }
-        });
+        .and_then(|auth_str| auth_str.strip_prefix("Bearer "))
+        .map(str::to_string);
 
     if let Some(token) = auth_header {
         match verify_token(&token) {
             Ok(claims) => {
-                
+                let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AppError::Unauthorized)?;
+
                 if let Ok(Some(_user)) = User::find()
-                    .filter(user::Column::Id.eq(uuid::Uuid::parse_str(&claims.sub).unwrap()))
+                    .filter(user::Column::Id.eq(user_id))
                     .one(&db)
                     .await
                 {
                     request.extensions_mut().insert(claims);
                     Ok(next.run(request).await)
                 } else {
-                    Err(StatusCode::UNAUTHORIZED)
+                    Err(AppError::Unauthorized)
                 }
             }
-            Err(_) => Err(StatusCode::UNAUTHORIZED),
+            Err(_) => Err(AppError::Unauthorized),
         }
     } else {
-        Err(StatusCode::UNAUTHORIZED)
+        Err(AppError::Unauthorized)
     }
 } 
\ No newline at end of file
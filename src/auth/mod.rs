@@ -1,11 +1,24 @@
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::env;
 use uuid::Uuid;
 use chrono::{Duration, Utc};
 
+use crate::error::AppError;
+
 pub mod middleware;
 
+/// Roles that work tickets: they may read and write internal notes and be assigned,
+/// mentioned and reported on.
+pub const STAFF_ROLES: &[&str] = &["agent", "kb_editor", "admin"];
+
+/// How long a satisfaction survey link stays valid.
+const SURVEY_TOKEN_DAYS: i64 = 30;
+const SURVEY_PURPOSE: &str = "csat";
+/// How long an anonymous help center visitor keeps the same session.
+pub const HELP_CENTER_SESSION_DAYS: i64 = 365;
+const HELP_CENTER_SESSION_PURPOSE: &str = "help_center_session";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String, 
@@ -15,6 +28,57 @@ pub struct Claims {
     pub iat: i64,
 }
 
+impl Claims {
+    /// The authenticated user's id; `auth_middleware` only admits tokens whose subject parses.
+    pub fn user_id(&self) -> Uuid {
+        Uuid::parse_str(&self.sub).expect("token subject is a valid user id")
+    }
+
+    /// Any of [`STAFF_ROLES`]; only staff may read or write internal notes.
+    pub fn is_staff(&self) -> bool {
+        STAFF_ROLES.contains(&self.role.as_str())
+    }
+
+    /// KB editors and admins approve, publish and archive knowledge base articles.
+    pub fn is_kb_editor(&self) -> bool {
+        matches!(self.role.as_str(), "kb_editor" | "admin")
+    }
+
+    /// Admins may change or remove what other users own, such as their comments.
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    /// `403 Forbidden` unless [`Claims::is_staff`].
+    pub fn require_staff(&self) -> Result<(), AppError> {
+        if self.is_staff() {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+
+    /// `403 Forbidden` unless [`Claims::is_kb_editor`].
+    pub fn require_kb_editor(&self) -> Result<(), AppError> {
+        if self.is_kb_editor() {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+}
+
+/// Claims in a token issued for one purpose, such as a satisfaction survey link or a
+/// help center session. They identify one thing and nothing else: a survey token is not
+/// a valid session token, nor the other way round.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurposeClaims {
+    pub sub: String,
+    pub purpose: String,
+    pub exp: i64,
+    pub iat: i64,
+}
+
 pub fn create_token(user_id: Uuid, email: &str, role: &str) -> Result<String, jsonwebtoken::errors::Error> {
     let secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     let expiration = Utc::now()
@@ -41,4 +105,58 @@ pub fn verify_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error>
         &Validation::default(),
     )?;
     Ok(token_data.claims)
-} 
\ No newline at end of file
+}
+
+pub fn create_survey_token(survey_id: Uuid) -> Result<String, jsonwebtoken::errors::Error> {
+    create_purpose_token(survey_id, SURVEY_PURPOSE, Duration::days(SURVEY_TOKEN_DAYS))
+}
+
+/// The survey a link's token was issued for.
+pub fn verify_survey_token(token: &str) -> Result<Uuid, jsonwebtoken::errors::Error> {
+    verify_purpose_token(token, SURVEY_PURPOSE)
+}
+
+/// Starts a session for an anonymous help center visitor, returning its ID and the
+/// token the visitor keeps it in. Being signed, the ID cannot be chosen by the visitor.
+pub fn create_help_center_session() -> Result<(Uuid, String), jsonwebtoken::errors::Error> {
+    let session_id = Uuid::new_v4();
+    let token = create_purpose_token(session_id, HELP_CENTER_SESSION_PURPOSE, Duration::days(HELP_CENTER_SESSION_DAYS))?;
+    Ok((session_id, token))
+}
+
+/// The help center session a token was issued for.
+pub fn verify_help_center_session(token: &str) -> Result<Uuid, jsonwebtoken::errors::Error> {
+    verify_purpose_token(token, HELP_CENTER_SESSION_PURPOSE)
+}
+
+fn create_purpose_token(id: Uuid, purpose: &str, valid_for: Duration) -> Result<String, jsonwebtoken::errors::Error> {
+    let secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    let expiration = Utc::now()
+        .checked_add_signed(valid_for)
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = PurposeClaims {
+        sub: id.to_string(),
+        purpose: purpose.to_string(),
+        exp: expiration,
+        iat: Utc::now().timestamp(),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref()))
+}
+
+fn verify_purpose_token(token: &str, purpose: &str) -> Result<Uuid, jsonwebtoken::errors::Error> {
+    let secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    let claims = decode::<PurposeClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_ref()),
+        &Validation::default(),
+    )?
+    .claims;
+
+    if claims.purpose != purpose {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+    Uuid::parse_str(&claims.sub).map_err(|_| ErrorKind::InvalidToken.into())
+}
\ No newline at end of file
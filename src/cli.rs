@@ -0,0 +1,147 @@
+use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set};
+use chrono::Utc;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::article_files;
+use crate::error::AppError;
+use crate::models::{User, user};
+use crate::validation::ROLES;
+use crate::handlers::knowledge_base_files::{self, ExportFormat};
+
+const USAGE: &str = "usage:
+  major                                     run the server
+  major kb import <path> --as <email>       import a directory, .md, .json, .tar or .tar.gz file
+  major kb export <path> [--format json] [--category <category>] [--status <status>]
+                                            export to a directory, a .tar.gz file or, with
+                                            --format json, a .json file
+  major user role <email> <role>            set a user's role, e.g. to create the first admin";
+
+/// Runs a maintenance command given on the command line instead of the server.
+pub async fn run(db: &DatabaseConnection, args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["kb", "import", path, options @ ..] => {
+            let mut options = parse_options(options, &["--as"])?;
+            let email = options.remove("--as").ok_or("--as <email> is required")?;
+            import(db, Path::new(path), email).await
+        }
+        ["kb", "export", path, options @ ..] => {
+            let mut options = parse_options(options, &["--format", "--category", "--status"])?;
+            let format = match options.remove("--format") {
+                None | Some("markdown") => ExportFormat::Markdown,
+                Some("json") => ExportFormat::Json,
+                Some(other) => return Err(format!("unknown format `{other}`, expected markdown or json")),
+            };
+            let category = options.remove("--category").map(str::to_string);
+            let status = options.remove("--status").map(str::to_string);
+            export(db, Path::new(path), format, category, status).await
+        }
+        ["user", "role", email, role] => set_role(db, email, role).await,
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+async fn set_role(db: &DatabaseConnection, email: &str, role: &str) -> Result<(), String> {
+    if !ROLES.contains(&role) {
+        return Err(format!("unknown role `{role}`, expected one of: {}", ROLES.join(", ")));
+    }
+
+    let user = User::find()
+        .filter(user::Column::Email.eq(email))
+        .one(db)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no user with the email {email}"))?;
+
+    let mut user: user::ActiveModel = user.into();
+    user.role = Set(role.to_string());
+    user.updated_at = Set(Utc::now());
+    user.update(db).await.map_err(|e| e.to_string())?;
+
+    println!("{email} is now {role}; the role applies from their next login");
+    Ok(())
+}
+
+/// Imports as the given KB editor or admin, who is recorded as the author of the changes.
+async fn import(db: &DatabaseConnection, path: &Path, email: &str) -> Result<(), String> {
+    let user = User::find()
+        .filter(user::Column::Email.eq(email))
+        .filter(user::Column::Role.is_in(["kb_editor", "admin"]))
+        .one(db)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no KB editor or admin with the email {email}"))?;
+
+    let entries = article_files::read_path(path).map_err(|e| e.to_string())?;
+    let summary = knowledge_base_files::import(db, entries, user.id)
+        .await
+        .map_err(describe)?;
+
+    for (label, slugs) in [("created", &summary.created), ("updated", &summary.updated)] {
+        println!("{label}: {}", slugs.len());
+        for slug in slugs {
+            println!("  {slug}");
+        }
+    }
+    println!("unchanged: {}", summary.unchanged.len());
+
+    Ok(())
+}
+
+async fn export(
+    db: &DatabaseConnection,
+    path: &Path,
+    format: ExportFormat,
+    category: Option<String>,
+    status: Option<String>,
+) -> Result<(), String> {
+    let articles = knowledge_base_files::export(db, category, status)
+        .await
+        .map_err(describe)?;
+
+    let name = path.to_string_lossy().to_lowercase();
+    let written = match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&articles)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string())),
+        ExportFormat::Markdown if name.ends_with(".tar.gz") || name.ends_with(".tgz") => {
+            article_files::write_archive(&articles)
+                .and_then(|archive| fs::write(path, archive))
+                .map_err(|e| e.to_string())
+        }
+        ExportFormat::Markdown => article_files::write_dir(path, &articles).map_err(|e| e.to_string()),
+    };
+    written?;
+
+    println!("exported {} articles to {}", articles.len(), path.display());
+    Ok(())
+}
+
+/// `--name value` pairs, allowing only the names given.
+fn parse_options<'a>(args: &[&'a str], allowed: &[&str]) -> Result<HashMap<&'a str, &'a str>, String> {
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(&name) = args.next() {
+        if !allowed.contains(&name) {
+            return Err(format!("unknown option `{name}`\n\n{USAGE}"));
+        }
+        let value = args.next().ok_or_else(|| format!("{name} needs a value"))?;
+        options.insert(name, *value);
+    }
+
+    Ok(options)
+}
+
+/// Lists each failing field of a validation error on its own line.
+fn describe(error: AppError) -> String {
+    match error {
+        AppError::Validation(errors) => errors
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        error => error.to_string(),
+    }
+}
\ No newline at end of file
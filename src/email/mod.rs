@@ -1,69 +1,170 @@
 use lettre::{
+    message::{Mailbox, MultiPart},
     transport::smtp::authentication::Credentials,
-    Message, SmtpTransport, Transport,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
-use std::env;
+use std::{env, future::Future};
+use uuid::Uuid;
+
+use crate::markdown;
 
 pub struct EmailService {
-    mailer: SmtpTransport,
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
 }
 
 impl EmailService {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let smtp_server = env::var("SMTP_SERVER").unwrap_or_else(|_| "smtp.gmail.com".to_string());
         let smtp_port = env::var("SMTP_PORT").unwrap_or_else(|_| "587".to_string());
-        let smtp_username = env::var("SMTP_USERNAME").expect("SMTP_USERNAME must be set");
-        let smtp_password = env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD must be set");
+        let smtp_username = env::var("SMTP_USERNAME").map_err(|_| "SMTP_USERNAME must be set")?;
+        let smtp_password = env::var("SMTP_PASSWORD").map_err(|_| "SMTP_PASSWORD must be set")?;
+        let from = env::var("FROM_EMAIL").map_err(|_| "FROM_EMAIL must be set")?.parse()?;
 
         let creds = Credentials::new(smtp_username, smtp_password);
 
-        let mailer = SmtpTransport::relay(&smtp_server)?
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp_server)?
             .port(smtp_port.parse()?)
             .credentials(creds)
             .build();
 
-        Ok(EmailService { mailer })
+        Ok(EmailService { mailer, from })
     }
 
-    pub async fn send_ticket_created(&self, to_email: &str, ticket_id: &str, subject: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// `comment` is Markdown; it is sent as both an HTML and a plain-text part.
+    pub async fn send_comment_notification(&self, to_email: &str, ticket_id: &str, comment_id: Uuid, comment: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let plain = format!(
+            "A new comment has been added to your support ticket.\n\nTicket ID: {}\n\n{}\n\nPlease log in to view the full conversation.",
+            ticket_id, markdown::to_plain_text(comment)
+        );
+        let html = format!(
+            "<p>A new comment has been added to your support ticket.</p>\n<p>Ticket ID: {}</p>\n<blockquote>{}</blockquote>\n<p>Please log in to view the full conversation.</p>",
+            ticket_id, markdown::to_html(comment)
+        );
+
         let email = Message::builder()
-            .from(env::var("FROM_EMAIL").expect("FROM_EMAIL must be set").parse()?)
+            .from(self.from.clone())
             .to(to_email.parse()?)
-            .subject("New Support Ticket Created")
-            .body(format!(
-                "Your support ticket has been created successfully.\n\nTicket ID: {}\nSubject: {}\n\nWe will get back to you soon.",
-                ticket_id, subject
-            ))?;
+            .subject("New Comment on Your Ticket")
+            .message_id(Some(self.comment_message_id(comment_id)))
+            .multipart(MultiPart::alternative_plain_html(plain, html))?;
 
-        self.mailer.send(&email)?;
+        self.mailer.send(email).await?;
         Ok(())
     }
 
-    pub async fn send_ticket_updated(&self, to_email: &str, ticket_id: &str, status: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// `comment` is the Markdown of the internal note the recipient was mentioned in.
+    pub async fn send_mention_notification(&self, to_email: &str, ticket_id: &str, comment_id: Uuid, mentioned_by: &str, comment: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let plain = format!(
+            "{} mentioned you in an internal note.\n\nTicket ID: {}\n\n{}",
+            mentioned_by, ticket_id, markdown::to_plain_text(comment)
+        );
+        let html = format!(
+            "<p>{} mentioned you in an internal note.</p>\n<p>Ticket ID: {}</p>\n<blockquote>{}</blockquote>",
+            ammonia::clean_text(mentioned_by), ticket_id, markdown::to_html(comment)
+        );
+
         let email = Message::builder()
-            .from(env::var("FROM_EMAIL").expect("FROM_EMAIL must be set").parse()?)
+            .from(self.from.clone())
             .to(to_email.parse()?)
-            .subject("Support Ticket Updated")
-            .body(format!(
-                "Your support ticket has been updated.\n\nTicket ID: {}\nNew Status: {}\n\nPlease check your ticket for more details.",
-                ticket_id, status
-            ))?;
+            .subject("You Were Mentioned on a Ticket")
+            .message_id(Some(self.comment_message_id(comment_id)))
+            .multipart(MultiPart::alternative_plain_html(plain, html))?;
 
-        self.mailer.send(&email)?;
+        self.mailer.send(email).await?;
         Ok(())
     }
 
-    pub async fn send_comment_notification(&self, to_email: &str, ticket_id: &str, comment: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn send_survey_request(&self, to_email: &str, ticket_id: &str, subject: &str, survey_url: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let email = Message::builder()
-            .from(env::var("FROM_EMAIL").expect("FROM_EMAIL must be set").parse()?)
+            .from(self.from.clone())
             .to(to_email.parse()?)
-            .subject("New Comment on Your Ticket")
+            .subject("How Did We Do?")
             .body(format!(
-                "A new comment has been added to your support ticket.\n\nTicket ID: {}\nComment: {}\n\nPlease log in to view the full conversation.",
-                ticket_id, comment
+                "Your support ticket has been resolved.\n\nTicket ID: {}\nSubject: {}\n\nPlease rate the help you received:\n{}\n\nThank you for your feedback.",
+                ticket_id, subject, survey_url
             ))?;
 
-        self.mailer.send(&email)?;
+        self.mailer.send(email).await?;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// A unique `Message-ID` for an email about a comment. Replies quote it in
+    /// `In-Reply-To`, which [`comment_id_from_message_id`] maps back to the comment.
+    fn comment_message_id(&self, comment_id: Uuid) -> String {
+        format!("<comment-{}.{}@{}>", comment_id, Uuid::new_v4().simple(), self.from.email.domain())
+    }
+} 
+
+/// The comment an email sent by [`EmailService`] was about, from its `Message-ID`.
+pub fn comment_id_from_message_id(message_id: &str) -> Option<Uuid> {
+    let id = message_id.trim().trim_start_matches('<').strip_prefix("comment-")?;
+    let (comment_id, _) = id.split_once('.')?;
+    Uuid::parse_str(comment_id).ok()
+}
+
+/// Emails the ticket's requester about a new public comment. Callers must not pass
+/// internal notes.
+pub fn notify_comment(to_email: String, ticket_id: Uuid, comment_id: Uuid, comment: String) {
+    spawn_notification(ticket_id, move |service| async move {
+        service
+            .send_comment_notification(&to_email, &ticket_id.to_string(), comment_id, &comment)
+            .await
+    });
+}
+
+/// Emails a staff member who was mentioned in an internal note.
+pub fn notify_mention(to_email: String, ticket_id: Uuid, comment_id: Uuid, mentioned_by: String, comment: String) {
+    spawn_notification(ticket_id, move |service| async move {
+        service
+            .send_mention_notification(&to_email, &ticket_id.to_string(), comment_id, &mentioned_by, &comment)
+            .await
+    });
+}
+
+/// Asks the requester of a resolved ticket to rate the support they received.
+pub fn notify_survey(to_email: String, ticket_id: Uuid, subject: String, survey_url: String) {
+    spawn_notification(ticket_id, move |service| async move {
+        service
+            .send_survey_request(&to_email, &ticket_id.to_string(), &subject, &survey_url)
+            .await
+    });
+}
+
+/// Sends in the background so a slow or unconfigured SMTP server never fails the
+/// request; errors are only logged.
+fn spawn_notification<F, Fut>(ticket_id: Uuid, send: F)
+where
+    F: FnOnce(EmailService) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send,
+{
+    tokio::spawn(async move {
+        let result = match EmailService::new() {
+            Ok(service) => send(service).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!(%ticket_id, "notification email not sent: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_id_from_message_id_reads_our_message_ids() {
+        let comment_id = Uuid::new_v4();
+        let message_id = format!("<comment-{}.{}@example.com>", comment_id, Uuid::new_v4().simple());
+
+        assert_eq!(comment_id_from_message_id(&message_id), Some(comment_id));
+        assert_eq!(comment_id_from_message_id(&format!(" {message_id} ")), Some(comment_id));
+    }
+
+    #[test]
+    fn comment_id_from_message_id_ignores_other_message_ids() {
+        assert_eq!(comment_id_from_message_id("<CAF=abc123@mail.gmail.com>"), None);
+        assert_eq!(comment_id_from_message_id("<comment-not-a-uuid.1@example.com>"), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,225 @@
+use axum::{
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{DbErr, SqlErr};
+use serde::Serialize;
+
+use crate::request_id;
+use crate::storage::StorageError;
+
+/// A single failing field in a `422 Unprocessable Entity` response.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: impl Into<String>, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Error type returned by every handler, rendered as an RFC 7807 `application/problem+json` body.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("the requested resource was not found")]
+    NotFound,
+    #[error("authentication is required")]
+    Unauthorized,
+    #[error("you do not have permission to perform this action")]
+    Forbidden,
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("the request failed validation")]
+    Validation(Vec<FieldError>),
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    #[error("{0}")]
+    UnsupportedMediaType(String),
+    /// An extractor rejected the request before the handler ran.
+    #[error("{detail}")]
+    Rejected { status: StatusCode, detail: String },
+    #[error(transparent)]
+    Database(#[from] DbErr),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    #[error(transparent)]
+    PasswordHash(#[from] bcrypt::BcryptError),
+    #[error(transparent)]
+    Token(#[from] jsonwebtoken::errors::Error),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    /// A server-side failure with no more specific variant; the detail is only logged.
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// Whether `err` is a unique constraint violation: two requests raced past a check
+/// for an existing row and the database rejected the second.
+pub fn is_unique_violation(err: &DbErr) -> bool {
+    matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+}
+
+impl AppError {
+    pub fn conflict(detail: impl Into<String>) -> Self {
+        AppError::Conflict(detail.into())
+    }
+
+    pub fn internal(detail: impl Into<String>) -> Self {
+        AppError::Internal(detail.into())
+    }
+
+    pub fn bad_request(detail: impl Into<String>) -> Self {
+        AppError::BadRequest(detail.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Rejected { status, .. } => *status,
+            AppError::Database(_)
+            | AppError::Serialization(_)
+            | AppError::PasswordHash(_)
+            | AppError::Token(_)
+            | AppError::Storage(_)
+            | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable code, also used as the last segment of the problem `type`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound => "not_found",
+            AppError::Unauthorized => "unauthorized",
+            AppError::Forbidden => "forbidden",
+            AppError::Conflict(_) => "conflict",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Rejected { .. } => "invalid_request",
+            AppError::Database(_) => "database_error",
+            AppError::Storage(_) => "storage_error",
+            AppError::Serialization(_)
+            | AppError::PasswordHash(_)
+            | AppError::Token(_)
+            | AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    kind: String,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "<[FieldError]>::is_empty")]
+    errors: &'a [FieldError],
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let request_id = request_id::current();
+
+        // Server-side failures are logged with their cause; clients only get a generic detail.
+        let detail = if status.is_server_error() {
+            tracing::error!(request_id = request_id.as_deref().unwrap_or("-"), error = ?self, "request failed");
+            "an unexpected error occurred".to_string()
+        } else {
+            self.to_string()
+        };
+
+        let errors = match &self {
+            AppError::Validation(errors) => errors.as_slice(),
+            _ => &[],
+        };
+
+        let problem = Problem {
+            kind: format!("/problems/{}", self.code().replace('_', "-")),
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail,
+            code: self.code(),
+            request_id,
+            errors,
+        };
+
+        let mut response = (status, axum::Json(problem)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        response
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::Rejected {
+            status: rejection.status(),
+            detail: rejection.body_text(),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::Rejected {
+            status: rejection.status(),
+            detail: rejection.body_text(),
+        }
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::Rejected {
+            status: rejection.status(),
+            detail: rejection.body_text(),
+        }
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppError::Rejected {
+            status: rejection.status(),
+            detail: rejection.body_text(),
+        }
+    }
+}
+
+impl From<MultipartError> for AppError {
+    fn from(error: MultipartError) -> Self {
+        AppError::Rejected {
+            status: error.status(),
+            detail: error.body_text(),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,410 @@
+use axum::{
+    body::Bytes,
+    extract::{State, Extension},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, QueryOrder, ModelTrait, Set,
+    sea_query::{Expr, SimpleExpr}
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::extract::{Json, Multipart, Path};
+use crate::error::AppError;
+use crate::validation::{MAX_ATTACHMENTS_PER_UPLOAD, MAX_ATTACHMENT_BYTES};
+use crate::models::{Attachment, Comment, Ticket, attachment, comment, ticket};
+use crate::storage::{StorageBackend, StorageError};
+use crate::auth::Claims;
+
+/// Multipart field that carries uploaded files; other fields are ignored.
+const FILE_FIELD: &str = "file";
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub comment_id: Option<Uuid>,
+    pub uploaded_by: Uuid,
+    pub file_name: String,
+    /// Detected from the file's bytes, not taken from the upload.
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+impl From<attachment::Model> for AttachmentResponse {
+    fn from(attachment: attachment::Model) -> Self {
+        AttachmentResponse {
+            id: attachment.id,
+            ticket_id: attachment.ticket_id,
+            comment_id: attachment.comment_id,
+            uploaded_by: attachment.uploaded_by,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+/// A file read from the request, not yet stored.
+struct Upload {
+    file_name: String,
+    content_type: String,
+    data: Bytes,
+}
+
+pub async fn upload_ticket_attachments(
+    State(db): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn StorageBackend>>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket_id): Path<Uuid>,
+    Multipart(multipart): Multipart,
+) -> Result<Json<Vec<AttachmentResponse>>, AppError> {
+    let ticket = find_accessible_ticket(&db, ticket_id, &claims).await?;
+    let uploads = read_uploads(multipart).await?;
+
+    let attachments = store_uploads(&db, storage.as_ref(), &claims, ticket.id, None, uploads).await?;
+
+    Ok(Json(attachments))
+}
+
+/// Only the comment's author or an admin may attach files to it.
+pub async fn upload_comment_attachments(
+    State(db): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn StorageBackend>>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, comment_id)): Path<(Uuid, Uuid)>,
+    Multipart(multipart): Multipart,
+) -> Result<Json<Vec<AttachmentResponse>>, AppError> {
+    let ticket = find_accessible_ticket(&db, ticket_id, &claims).await?;
+
+    let comment = Comment::find_by_id(comment_id)
+        .filter(comment::Column::TicketId.eq(ticket.id))
+        .filter(comment::Column::DeletedAt.is_null())
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if comment.user_id != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let uploads = read_uploads(multipart).await?;
+
+    let attachments = store_uploads(&db, storage.as_ref(), &claims, ticket.id, Some(comment.id), uploads).await?;
+
+    Ok(Json(attachments))
+}
+
+pub async fn get_ticket_attachments(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<Vec<AttachmentResponse>>, AppError> {
+    let ticket = find_accessible_ticket(&db, ticket_id, &claims).await?;
+
+    let attachments = Attachment::find()
+        .filter(attachment::Column::TicketId.eq(ticket.id))
+        .filter(visible_to(&claims))
+        .order_by_asc(attachment::Column::CreatedAt)
+        .all(&db)
+        .await?;
+
+    Ok(Json(attachments.into_iter().map(AttachmentResponse::from).collect()))
+}
+
+/// Always served as a download so uploaded HTML or SVG never renders in our origin.
+pub async fn download_attachment(
+    State(db): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn StorageBackend>>,
+    Extension(claims): Extension<Claims>,
+    Path(attachment_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let attachment = find_visible_attachment(&db, attachment_id, &claims).await?;
+
+    let data = storage
+        .get(&attachment.storage_key)
+        .await
+        .map_err(|e| match e {
+            StorageError::NotFound => AppError::NotFound,
+            e => e.into(),
+        })?;
+
+    let content_type = HeaderValue::from_str(&attachment.content_type)
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    let disposition = HeaderValue::from_str(&content_disposition(&attachment.file_name))
+        .unwrap_or(HeaderValue::from_static("attachment"));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// Only the uploader or an admin may delete an attachment.
+pub async fn delete_attachment(
+    State(db): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn StorageBackend>>,
+    Extension(claims): Extension<Claims>,
+    Path(attachment_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let attachment = find_visible_attachment(&db, attachment_id, &claims).await?;
+
+    if attachment.uploaded_by != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let storage_key = attachment.storage_key.clone();
+    attachment.delete(&db).await?;
+    discard(storage.as_ref(), &[storage_key]).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes stored objects whose rows are gone or were never written. Failures only
+/// leave an orphaned object behind, so they are logged rather than returned.
+pub(crate) async fn discard(storage: &dyn StorageBackend, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            tracing::warn!(key, "failed to delete stored attachment: {e}");
+        }
+    }
+}
+
+/// Customers may only touch attachments on their own tickets.
+async fn find_accessible_ticket(
+    db: &DatabaseConnection,
+    ticket_id: Uuid,
+    claims: &Claims,
+) -> Result<ticket::Model, AppError> {
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !claims.is_staff() && ticket.created_by != claims.user_id() {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(ticket)
+}
+
+async fn find_visible_attachment(
+    db: &DatabaseConnection,
+    attachment_id: Uuid,
+    claims: &Claims,
+) -> Result<attachment::Model, AppError> {
+    let attachment = Attachment::find_by_id(attachment_id)
+        .filter(visible_to(claims))
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    find_accessible_ticket(db, attachment.ticket_id, claims).await?;
+
+    Ok(attachment)
+}
+
+/// Attachments on deleted comments are hidden from everyone, and those on internal
+/// notes from customers.
+fn visible_to(claims: &Claims) -> SimpleExpr {
+    let comments = if claims.is_staff() {
+        "SELECT id FROM comments WHERE deleted_at IS NULL"
+    } else {
+        "SELECT id FROM comments WHERE deleted_at IS NULL AND NOT is_internal"
+    };
+
+    Expr::cust(format!(
+        "(attachments.comment_id IS NULL OR attachments.comment_id IN ({comments}))"
+    ))
+}
+
+/// Reads every `file` field, enforcing the size and count limits before anything is stored.
+async fn read_uploads(mut multipart: axum::extract::Multipart) -> Result<Vec<Upload>, AppError> {
+    let mut uploads = Vec::new();
+
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+        if uploads.len() == MAX_ATTACHMENTS_PER_UPLOAD {
+            return Err(AppError::bad_request(format!(
+                "at most {MAX_ATTACHMENTS_PER_UPLOAD} files can be uploaded at once"
+            )));
+        }
+
+        let file_name = sanitize_file_name(field.file_name());
+        let mut data = Vec::new();
+        while let Some(chunk) = field.chunk().await? {
+            if data.len() + chunk.len() > MAX_ATTACHMENT_BYTES {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "{file_name} is larger than {} MiB",
+                    MAX_ATTACHMENT_BYTES / (1024 * 1024)
+                )));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        if data.is_empty() {
+            return Err(AppError::bad_request(format!("{file_name} is empty")));
+        }
+
+        uploads.push(Upload {
+            file_name,
+            content_type: sniff_content_type(&data)?,
+            data: data.into(),
+        });
+    }
+
+    if uploads.is_empty() {
+        return Err(AppError::bad_request(format!("expected at least one `{FILE_FIELD}` field")));
+    }
+
+    Ok(uploads)
+}
+
+/// Writes the files to storage, then records them in one insert. Objects are removed
+/// again if either step fails part-way.
+async fn store_uploads(
+    db: &DatabaseConnection,
+    storage: &dyn StorageBackend,
+    claims: &Claims,
+    ticket_id: Uuid,
+    comment_id: Option<Uuid>,
+    uploads: Vec<Upload>,
+) -> Result<Vec<AttachmentResponse>, AppError> {
+    let now = Utc::now();
+    let mut models = Vec::with_capacity(uploads.len());
+    let mut keys = Vec::with_capacity(uploads.len());
+
+    for upload in uploads {
+        let id = Uuid::new_v4();
+        let storage_key = format!("tickets/{ticket_id}/{id}");
+
+        if let Err(e) = storage.put(&storage_key, &upload.content_type, upload.data.clone()).await {
+            discard(storage, &keys).await;
+            return Err(e.into());
+        }
+        keys.push(storage_key.clone());
+
+        models.push(attachment::Model {
+            id,
+            ticket_id,
+            comment_id,
+            uploaded_by: claims.user_id(),
+            file_name: upload.file_name,
+            content_type: upload.content_type,
+            size_bytes: upload.data.len() as i64,
+            storage_key,
+            created_at: now,
+        });
+    }
+
+    let rows = models.iter().cloned().map(|model| attachment::ActiveModel {
+        id: Set(model.id),
+        ticket_id: Set(model.ticket_id),
+        comment_id: Set(model.comment_id),
+        uploaded_by: Set(model.uploaded_by),
+        file_name: Set(model.file_name),
+        content_type: Set(model.content_type),
+        size_bytes: Set(model.size_bytes),
+        storage_key: Set(model.storage_key),
+        created_at: Set(model.created_at),
+    });
+
+    if let Err(e) = Attachment::insert_many(rows).exec(db).await {
+        discard(storage, &keys).await;
+        return Err(e.into());
+    }
+
+    Ok(models.into_iter().map(AttachmentResponse::from).collect())
+}
+
+/// Executables are refused outright; anything unrecognised is served as plain text
+/// when it decodes as UTF-8 (logs, CSVs) and as opaque bytes otherwise.
+fn sniff_content_type(data: &[u8]) -> Result<String, AppError> {
+    match infer::get(data) {
+        Some(kind) if kind.matcher_type() == infer::MatcherType::App => Err(AppError::UnsupportedMediaType(
+            format!("{} files are not accepted", kind.mime_type()),
+        )),
+        Some(kind) => Ok(kind.mime_type().to_string()),
+        None if std::str::from_utf8(data).is_ok() => Ok("text/plain; charset=utf-8".to_string()),
+        None => Ok("application/octet-stream".to_string()),
+    }
+}
+
+/// Drops any client-side directory, control characters and excess length.
+fn sanitize_file_name(file_name: Option<&str>) -> String {
+    let base = file_name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default();
+
+    let name: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(255)
+        .collect();
+
+    match name.trim() {
+        "" | "." | ".." => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// `attachment` disposition with an ASCII fallback name and the exact name per RFC 5987.
+fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+
+    let encoded: String = file_name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_file_name_keeps_only_the_base_name() {
+        assert_eq!(sanitize_file_name(Some("../../etc/passwd")), "passwd");
+        assert_eq!(sanitize_file_name(Some("C:\\Users\\me\\report.pdf")), "report.pdf");
+        assert_eq!(sanitize_file_name(Some("line\nbreak\u{0}.txt")), "linebreak.txt");
+    }
+
+    #[test]
+    fn sanitize_file_name_falls_back_for_empty_names() {
+        assert_eq!(sanitize_file_name(None), "attachment");
+        assert_eq!(sanitize_file_name(Some("  ")), "attachment");
+        assert_eq!(sanitize_file_name(Some("uploads/..")), "attachment");
+    }
+
+    #[test]
+    fn content_disposition_escapes_both_names() {
+        assert_eq!(
+            content_disposition("résumé \"final\".pdf"),
+            "attachment; filename=\"r_sum_ _final_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf",
+        );
+        assert_eq!(content_disposition("notes.txt"), "attachment; filename=\"notes.txt\"; filename*=UTF-8''notes.txt");
+    }
+}
\ No newline at end of file
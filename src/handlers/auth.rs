@@ -1,29 +1,39 @@
-use axum::{
-    extract::State,
-    http::StatusCode,
-    Json,
-};
+use axum::extract::State;
 use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use validator::Validate;
 
+use crate::extract::{Json, ValidatedJson};
+use crate::error::AppError;
 use crate::models::{User, user};
 use crate::auth::create_token;
+use crate::validation::{self, CUSTOMER_ROLE, MAX_EMAIL_LEN, MAX_NAME_LEN, MAX_PASSWORD_LEN, MIN_PASSWORD_LEN};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(length(min = 1, max = "MAX_EMAIL_LEN"))]
     pub email: String,
+    #[validate(length(min = 1, max = "MAX_PASSWORD_LEN"))]
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
+    #[validate(length(min = 1, max = "MAX_NAME_LEN"), custom = "validation::not_blank")]
     pub name: String,
+    #[validate(email, length(max = "MAX_EMAIL_LEN"))]
     pub email: String,
+    #[validate(
+        length(min = "MIN_PASSWORD_LEN", max = "MAX_PASSWORD_LEN"),
+        custom = "validation::password_strength"
+    )]
     pub password: String,
-    pub role: String,
+    /// Only `customer` may be registered; staff roles are granted by an admin.
+    #[validate(custom = "validation::role")]
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -40,54 +50,61 @@ pub struct UserResponse {
     pub role: String,
 }
 
+impl From<user::Model> for UserResponse {
+    fn from(user: user::Model) -> Self {
+        UserResponse {
+            id: user.id,
+            name: user.name,
+            email: user.email,
+            role: user.role,
+        }
+    }
+}
+
 pub async fn login(
     State(db): State<DatabaseConnection>,
-    Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
     let user = User::find()
         .filter(user::Column::Email.eq(&payload.email))
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .await?
+        .ok_or(AppError::Unauthorized)?;
 
-    if !verify(&payload.password, &user.password_hash)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    if !verify(&payload.password, &user.password_hash)?
     {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(AppError::Unauthorized);
     }
 
-    let token = create_token(user.id, &user.email, &user.role)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let token = create_token(user.id, &user.email, &user.role)?;
 
     Ok(Json(AuthResponse {
         token,
-        user: UserResponse {
-            id: user.id,
-            name: user.name,
-            email: user.email,
-            role: user.role,
-        },
+        user: user.into(),
     }))
 }
 
+/// Self-registration always creates a customer. Asking for any other role is refused
+/// rather than ignored, so a client expecting a staff account finds out.
 pub async fn register(
     State(db): State<DatabaseConnection>,
-    Json(payload): Json<RegisterRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    if payload.role.as_deref().is_some_and(|role| role != CUSTOMER_ROLE) {
+        return Err(AppError::Forbidden);
+    }
+
     // Check if user already exists
     let existing_user = User::find()
         .filter(user::Column::Email.eq(&payload.email))
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     if existing_user.is_some() {
-        return Err(StatusCode::CONFLICT);
+        return Err(AppError::conflict("a user with this email already exists"));
     }
 
-    let password_hash = hash(&payload.password, DEFAULT_COST)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let password_hash = hash(&payload.password, DEFAULT_COST)?;
 
     let user_id = Uuid::new_v4();
     let now = Utc::now();
@@ -97,23 +114,17 @@ pub async fn register(
         name: Set(payload.name),
         email: Set(payload.email),
         password_hash: Set(password_hash),
-        role: Set(payload.role),
+        role: Set(CUSTOMER_ROLE.to_string()),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
-    let user = user.insert(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let user = user.insert(&db).await?;
 
-    let token = create_token(user.id, &user.email, &user.role)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let token = create_token(user.id, &user.email, &user.role)?;
 
     Ok(Json(AuthResponse {
         token,
-        user: UserResponse {
-            id: user.id,
-            name: user.name,
-            email: user.email,
-            role: user.role,
-        },
+        user: user.into(),
     }))
 } 
\ No newline at end of file
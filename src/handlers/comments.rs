@@ -1,86 +1,382 @@
 use axum::{
-    extract::{Path, State, Extension},
+    extract::{State, Extension},
     http::StatusCode,
-    Json,
 };
-use sea_orm::{DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder,
+    TransactionTrait, Condition, DbErr
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+use validator::Validate;
 
-use crate::models::{Comment, comment};
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::{self, MAX_BODY_LEN};
+use crate::models::{Comment, CommentRevision, Ticket, User, comment, comment_revision, ticket};
 use crate::auth::Claims;
+use crate::email;
+use crate::markdown;
+use crate::handlers::mentions;
+use crate::ws::{WsMessage, WsState};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateCommentRequest {
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
     pub content: String,
     pub is_internal: bool,
+    /// The comment on the same ticket this one replies to.
+    pub parent_comment_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCommentRequest {
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentListQuery {
+    /// Nest replies under the comment they answer instead of returning a flat list.
+    #[serde(default)]
+    pub threaded: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CommentResponse {
     pub id: Uuid,
     pub ticket_id: Uuid,
     pub user_id: Uuid,
+    pub parent_comment_id: Option<Uuid>,
+    /// Markdown source; empty once the comment has been deleted.
     pub content: String,
+    /// `content` rendered to sanitised HTML.
+    pub content_html: String,
     pub is_internal: bool,
+    pub is_edited: bool,
+    pub is_deleted: bool,
+    pub created_at: chrono::DateTime<Utc>,
+    pub edited_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentThread {
+    #[serde(flatten)]
+    pub comment: CommentResponse,
+    pub replies: Vec<CommentThread>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum CommentList {
+    Flat(Vec<CommentResponse>),
+    Threaded(Vec<CommentThread>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentRevisionResponse {
+    pub id: Uuid,
+    pub comment_id: Uuid,
+    /// Content as it was before this edit.
+    pub content: String,
+    pub edited_by: Uuid,
     pub created_at: chrono::DateTime<Utc>,
 }
 
+impl From<comment::Model> for CommentResponse {
+    fn from(comment: comment::Model) -> Self {
+        let is_deleted = comment.deleted_at.is_some();
+        CommentResponse {
+            id: comment.id,
+            ticket_id: comment.ticket_id,
+            user_id: comment.user_id,
+            parent_comment_id: comment.parent_comment_id,
+            content_html: if is_deleted { String::new() } else { markdown::to_html(&comment.content) },
+            content: if is_deleted { String::new() } else { comment.content },
+            is_internal: comment.is_internal,
+            is_edited: comment.edited_at.is_some(),
+            is_deleted,
+            created_at: comment.created_at,
+            edited_at: comment.edited_at,
+        }
+    }
+}
+
+/// `@mentions` in internal notes notify the mentioned agents.
 pub async fn create_comment(
     State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
+    Extension(claims): Extension<Claims>,
     Path(ticket_id): Path<Uuid>,
-    Json(payload): Json<CreateCommentRequest>,
-) -> Result<Json<CommentResponse>, StatusCode> {
-    let user_id = uuid::Uuid::new_v4();
+    ValidatedJson(payload): ValidatedJson<CreateCommentRequest>,
+) -> Result<Json<CommentResponse>, AppError> {
+    if payload.is_internal && !claims.is_staff() {
+        return Err(AppError::Forbidden);
+    }
+
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if let Some(parent_id) = payload.parent_comment_id {
+        let parent = Comment::find_by_id(parent_id)
+            .filter(comment::Column::TicketId.eq(ticket_id))
+            .one(&db)
+            .await?
+            .filter(|parent| claims.is_staff() || !parent.is_internal)
+            .ok_or_else(|| AppError::bad_request("parent comment not found on this ticket"))?;
+        if parent.is_internal && !payload.is_internal {
+            return Err(AppError::bad_request("a public comment cannot reply to an internal note"));
+        }
+    }
+
     let comment_id = Uuid::new_v4();
     let now = Utc::now();
 
     let comment = comment::ActiveModel {
         id: Set(comment_id),
         ticket_id: Set(ticket_id),
-        user_id: Set(user_id),
+        user_id: Set(claims.user_id()),
         content: Set(payload.content),
         is_internal: Set(payload.is_internal),
         created_at: Set(now),
+        edited_at: Set(None),
+        deleted_at: Set(None),
+        deleted_by: Set(None),
+        parent_comment_id: Set(payload.parent_comment_id),
+        email_message_id: Set(None),
     };
 
-    let comment = comment.insert(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let comment = comment.insert(&db).await?;
+
+    notify_comment_created(&db, &ws, &ticket, &comment, &claims).await;
 
-    Ok(Json(CommentResponse {
-        id: comment.id,
+    Ok(Json(comment.into()))
+}
+
+/// Notifications for a newly posted comment: a `NewComment` event, mentioned agents
+/// for internal notes, the ticket's requester by email for public replies. Called once
+/// the comment is saved, so a failure here is only logged rather than failing the request.
+pub(crate) async fn notify_comment_created(
+    db: &DatabaseConnection,
+    ws: &WsState,
+    ticket: &ticket::Model,
+    comment: &comment::Model,
+    author: &Claims,
+) {
+    publish_comment(ws, ticket, comment);
+    let notified = async {
+        mentions::record_mentions(db, ws, ticket, comment, author).await?;
+        notify_requester(db, ticket, comment).await
+    };
+    if let Err(e) = notified.await {
+        tracing::warn!(comment_id = %comment.id, "comment notifications not sent: {e}");
+    }
+}
+
+/// Internal notes only reach staff connections; see `WsMessage::visible_to`.
+pub(crate) fn publish_comment(ws: &WsState, ticket: &ticket::Model, comment: &comment::Model) {
+    ws.publish(WsMessage::NewComment {
         ticket_id: comment.ticket_id,
-        user_id: comment.user_id,
-        content: comment.content,
-        is_internal: comment.is_internal,
-        created_at: comment.created_at,
-    }))
+        requester: ticket.created_by,
+        comment: comment.clone().into(),
+    });
 }
 
+/// Emails a public comment to the ticket's requester, unless they wrote it.
+pub(crate) async fn notify_requester(
+    db: &DatabaseConnection,
+    ticket: &ticket::Model,
+    comment: &comment::Model,
+) -> Result<(), DbErr> {
+    if !comment.is_internal && ticket.created_by != comment.user_id {
+        if let Some(requester) = User::find_by_id(ticket.created_by).one(db).await? {
+            email::notify_comment(requester.email, ticket.id, comment.id, comment.content.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Internal notes are left out unless the caller is staff. In a threaded list, replies
+/// whose parent the caller cannot see are shown at the top level.
 pub async fn get_ticket_comments(
     State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
     Path(ticket_id): Path<Uuid>,
-) -> Result<Json<Vec<CommentResponse>>, StatusCode> {
-    let condition = comment::Column::TicketId.eq(ticket_id);
+    Query(query): Query<CommentListQuery>,
+) -> Result<Json<CommentList>, AppError> {
+    let mut condition = Condition::all().add(comment::Column::TicketId.eq(ticket_id));
+    if !claims.is_staff() {
+        condition = condition.add(comment::Column::IsInternal.eq(false));
+    }
 
     let comments = Comment::find()
         .filter(condition)
         .order_by_asc(comment::Column::CreatedAt)
         .all(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     let responses: Vec<CommentResponse> = comments
         .into_iter()
-        .map(|comment| CommentResponse {
-            id: comment.id,
-            ticket_id: comment.ticket_id,
-            user_id: comment.user_id,
-            content: comment.content,
-            is_internal: comment.is_internal,
-            created_at: comment.created_at,
+        .map(CommentResponse::from)
+        .collect();
+
+    if query.threaded {
+        Ok(Json(CommentList::Threaded(thread(responses))))
+    } else {
+        Ok(Json(CommentList::Flat(responses)))
+    }
+}
+
+/// Replaces the content, keeping the previous version as a revision. Mentions added
+/// by the edit are notified like those in a new note.
+pub async fn update_comment(
+    State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, comment_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<UpdateCommentRequest>,
+) -> Result<Json<CommentResponse>, AppError> {
+    let comment = find_editable_comment(&db, ticket_id, comment_id, &claims).await?;
+
+    if comment.content == payload.content {
+        return Ok(Json(comment.into()));
+    }
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+
+    let revision = comment_revision::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        comment_id: Set(comment.id),
+        content: Set(comment.content.clone()),
+        edited_by: Set(claims.user_id()),
+        created_at: Set(now),
+    };
+    revision.insert(&txn).await?;
+
+    let mut comment: comment::ActiveModel = comment.into();
+    comment.content = Set(payload.content);
+    comment.edited_at = Set(Some(now));
+    let comment = comment.update(&txn).await?;
+
+    txn.commit().await?;
+
+    // The edit is saved; a failed notification must not turn it into an error.
+    if let Err(e) = mentions::record_mentions(&db, &ws, &ticket, &comment, &claims).await {
+        tracing::warn!(comment_id = %comment.id, "mention notifications not sent: {e}");
+    }
+
+    Ok(Json(comment.into()))
+}
+
+/// Soft-deletes the comment: it stays in the thread as a tombstone and its content
+/// and revisions remain in the database for auditing.
+pub async fn delete_comment(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    let comment = find_editable_comment(&db, ticket_id, comment_id, &claims).await?;
+
+    let mut comment: comment::ActiveModel = comment.into();
+    comment.deleted_at = Set(Some(Utc::now()));
+    comment.deleted_by = Set(Some(claims.user_id()));
+    comment.update(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_comment_revisions(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<CommentRevisionResponse>>, AppError> {
+    let comment = find_comment(&db, ticket_id, comment_id).await?;
+
+    if comment.user_id != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    let revisions = CommentRevision::find()
+        .filter(comment_revision::Column::CommentId.eq(comment.id))
+        .order_by_desc(comment_revision::Column::CreatedAt)
+        .all(&db)
+        .await?;
+
+    let responses: Vec<CommentRevisionResponse> = revisions
+        .into_iter()
+        .map(|revision| CommentRevisionResponse {
+            id: revision.id,
+            comment_id: revision.comment_id,
+            content: revision.content,
+            edited_by: revision.edited_by,
+            created_at: revision.created_at,
         })
         .collect();
 
     Ok(Json(responses))
-} 
\ No newline at end of file
+}
+
+/// Arranges comments into reply trees, keeping each level in the given order.
+fn thread(comments: Vec<CommentResponse>) -> Vec<CommentThread> {
+    fn build(comment: CommentResponse, replies: &mut HashMap<Uuid, Vec<CommentResponse>>) -> CommentThread {
+        let children = replies.remove(&comment.id).unwrap_or_default();
+        CommentThread {
+            comment,
+            replies: children.into_iter().map(|reply| build(reply, replies)).collect(),
+        }
+    }
+
+    let ids: HashSet<Uuid> = comments.iter().map(|comment| comment.id).collect();
+    let mut roots = Vec::new();
+    let mut replies: HashMap<Uuid, Vec<CommentResponse>> = HashMap::new();
+
+    for comment in comments {
+        match comment.parent_comment_id.filter(|parent_id| ids.contains(parent_id)) {
+            Some(parent_id) => replies.entry(parent_id).or_default().push(comment),
+            None => roots.push(comment),
+        }
+    }
+
+    roots.into_iter().map(|root| build(root, &mut replies)).collect()
+}
+
+async fn find_comment(
+    db: &DatabaseConnection,
+    ticket_id: Uuid,
+    comment_id: Uuid,
+) -> Result<comment::Model, AppError> {
+    Comment::find_by_id(comment_id)
+        .filter(comment::Column::TicketId.eq(ticket_id))
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// Only the author or an admin may change a comment, and deleted comments are final.
+async fn find_editable_comment(
+    db: &DatabaseConnection,
+    ticket_id: Uuid,
+    comment_id: Uuid,
+    claims: &Claims,
+) -> Result<comment::Model, AppError> {
+    let comment = find_comment(db, ticket_id, comment_id).await?;
+
+    if comment.deleted_at.is_some() {
+        return Err(AppError::NotFound);
+    }
+    if comment.user_id != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(comment)
+}
\ No newline at end of file
@@ -0,0 +1,282 @@
+use axum::extract::{State, Extension};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    FromQueryResult, JoinType, Select, sea_query::Expr
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::extract::{Json, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::MAX_BODY_LEN;
+use crate::models::{CsatSurvey, Ticket, User, csat_survey, ticket, user};
+use crate::auth::{self, Claims};
+use crate::email;
+
+/// Survey page used when `CSAT_SURVEY_URL` is not set.
+const DEFAULT_SURVEY_URL: &str = "http://localhost:3000/csat";
+
+#[derive(Debug, Deserialize)]
+pub struct SurveyTokenQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubmitSurveyRequest {
+    /// The token from the survey link.
+    pub token: String,
+    #[validate(range(min = 1, max = 5))]
+    pub rating: i16,
+    #[validate(length(max = "MAX_BODY_LEN"))]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsatPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl CsatPeriod {
+    fn as_str(self) -> &'static str {
+        match self {
+            CsatPeriod::Day => "day",
+            CsatPeriod::Week => "week",
+            CsatPeriod::Month => "month",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CsatSummaryQuery {
+    /// Surveys sent at or after this time.
+    pub since: Option<chrono::DateTime<Utc>>,
+    /// Surveys sent before this time.
+    pub until: Option<chrono::DateTime<Utc>>,
+    pub agent_id: Option<Uuid>,
+    /// Adds a breakdown by day, week or month.
+    pub period: Option<CsatPeriod>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SurveyResponse {
+    pub ticket_id: Uuid,
+    pub ticket_subject: String,
+    pub rating: Option<i16>,
+    pub comment: Option<String>,
+    pub responded_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct CsatStats {
+    pub surveys_sent: i64,
+    pub responses: i64,
+    pub average_rating: Option<f64>,
+    /// Percentage of responses rated 4 or 5.
+    pub satisfaction: Option<f64>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct AgentCsatStats {
+    /// `None` for tickets that were unassigned when resolved.
+    pub agent_id: Option<Uuid>,
+    pub agent_name: Option<String>,
+    pub surveys_sent: i64,
+    pub responses: i64,
+    pub average_rating: Option<f64>,
+    pub satisfaction: Option<f64>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct PeriodCsatStats {
+    pub period_start: chrono::DateTime<Utc>,
+    pub surveys_sent: i64,
+    pub responses: i64,
+    pub average_rating: Option<f64>,
+    pub satisfaction: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsatSummaryResponse {
+    /// The whole support team.
+    pub overall: CsatStats,
+    pub by_agent: Vec<AgentCsatStats>,
+    /// Only present when `period` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub by_period: Option<Vec<PeriodCsatStats>>,
+}
+
+/// The survey a link points to, so the survey page can show the ticket and any
+/// earlier answer.
+pub async fn get_survey(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<SurveyTokenQuery>,
+) -> Result<Json<SurveyResponse>, AppError> {
+    let (survey, ticket) = find_survey(&db, &query.token).await?;
+
+    Ok(Json(survey_response(survey, ticket)))
+}
+
+/// Records the customer's rating. The answer can be changed while the link is valid.
+pub async fn submit_survey(
+    State(db): State<DatabaseConnection>,
+    ValidatedJson(payload): ValidatedJson<SubmitSurveyRequest>,
+) -> Result<Json<SurveyResponse>, AppError> {
+    let (survey, ticket) = find_survey(&db, &payload.token).await?;
+
+    let mut survey: csat_survey::ActiveModel = survey.into();
+    survey.rating = Set(Some(payload.rating));
+    survey.comment = Set(payload.comment.filter(|comment| !comment.trim().is_empty()));
+    survey.responded_at = Set(Some(Utc::now()));
+    let survey = survey.update(&db).await?;
+
+    Ok(Json(survey_response(survey, ticket)))
+}
+
+/// CSAT for surveys sent in the period, overall and per agent, optionally broken
+/// down by day, week or month.
+pub async fn get_csat_summary(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<CsatSummaryQuery>,
+) -> Result<Json<CsatSummaryResponse>, AppError> {
+    claims.require_staff()?;
+
+    let mut select = CsatSurvey::find().select_only();
+    if let Some(since) = query.since {
+        select = select.filter(csat_survey::Column::SentAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(csat_survey::Column::SentAt.lt(until));
+    }
+    if let Some(agent_id) = query.agent_id {
+        select = select.filter(csat_survey::Column::AgentId.eq(agent_id));
+    }
+
+    let overall = with_stats(select.clone())
+        .into_model::<CsatStats>()
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let by_agent = with_stats(select.clone())
+        .column(csat_survey::Column::AgentId)
+        .column_as(user::Column::Name, "agent_name")
+        .join(
+            JoinType::LeftJoin,
+            csat_survey::Entity::belongs_to(User)
+                .from(csat_survey::Column::AgentId)
+                .to(user::Column::Id)
+                .into(),
+        )
+        .group_by(csat_survey::Column::AgentId)
+        .group_by(user::Column::Name)
+        .order_by_asc(user::Column::Name)
+        .into_model::<AgentCsatStats>()
+        .all(&db)
+        .await?;
+
+    let by_period = match query.period {
+        Some(period) => {
+            let period_start = format!("date_trunc('{}', csat_surveys.sent_at)", period.as_str());
+            Some(
+                with_stats(select)
+                    .column_as(Expr::cust(&period_start), "period_start")
+                    .group_by(Expr::cust(&period_start))
+                    .order_by_asc(Expr::cust(&period_start))
+                    .into_model::<PeriodCsatStats>()
+                    .all(&db)
+                    .await?,
+            )
+        }
+        None => None,
+    };
+
+    Ok(Json(CsatSummaryResponse { overall, by_agent, by_period }))
+}
+
+/// Starts a survey for a ticket that has just been resolved and emails the link to
+/// its requester. Tickets raised by staff are not surveyed. Called once the resolution
+/// is saved, so a failure here is only logged rather than failing the update.
+pub(crate) async fn send_survey(db: &DatabaseConnection, ticket: &ticket::Model) {
+    if let Err(e) = start_survey(db, ticket).await {
+        tracing::warn!(ticket_id = %ticket.id, "CSAT survey not sent: {e}");
+    }
+}
+
+async fn start_survey(db: &DatabaseConnection, ticket: &ticket::Model) -> Result<(), AppError> {
+    let requester = match User::find_by_id(ticket.created_by).one(db).await? {
+        Some(requester) if requester.role == "customer" => requester,
+        _ => return Ok(()),
+    };
+
+    let survey = csat_survey::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        ticket_id: Set(ticket.id),
+        agent_id: Set(ticket.assigned_to),
+        customer_id: Set(requester.id),
+        rating: Set(None),
+        comment: Set(None),
+        sent_at: Set(Utc::now()),
+        responded_at: Set(None),
+    };
+    let survey = survey.insert(db).await?;
+
+    let token = auth::create_survey_token(survey.id)?;
+    let base_url = env::var("CSAT_SURVEY_URL").unwrap_or_else(|_| DEFAULT_SURVEY_URL.to_string());
+    email::notify_survey(
+        requester.email,
+        ticket.id,
+        ticket.subject.clone(),
+        format!("{base_url}?token={token}"),
+    );
+
+    Ok(())
+}
+
+async fn find_survey(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<(csat_survey::Model, ticket::Model), AppError> {
+    let survey_id = auth::verify_survey_token(token).map_err(|_| AppError::Unauthorized)?;
+
+    let survey = CsatSurvey::find_by_id(survey_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let ticket = Ticket::find_by_id(survey.ticket_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok((survey, ticket))
+}
+
+fn with_stats(select: Select<CsatSurvey>) -> Select<CsatSurvey> {
+    select
+        .column_as(Expr::cust("count(*)"), "surveys_sent")
+        .column_as(Expr::cust("count(csat_surveys.rating)"), "responses")
+        .column_as(Expr::cust("avg(csat_surveys.rating)::float8"), "average_rating")
+        .column_as(
+            Expr::cust(
+                "(100.0 * count(*) FILTER (WHERE csat_surveys.rating >= 4) \
+                 / NULLIF(count(csat_surveys.rating), 0))::float8",
+            ),
+            "satisfaction",
+        )
+}
+
+fn survey_response(survey: csat_survey::Model, ticket: ticket::Model) -> SurveyResponse {
+    SurveyResponse {
+        ticket_id: ticket.id,
+        ticket_subject: ticket.subject,
+        rating: survey.rating,
+        comment: survey.comment,
+        responded_at: survey.responded_at,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,161 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Redirect, Response},
+};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::extract::{Json, Path, Query};
+use crate::error::AppError;
+use crate::locale;
+use crate::validation::LOCALES;
+use crate::models::KnowledgeBase;
+use crate::pagination::Page;
+use crate::handlers::knowledge_base::{self, ArticleListItem, ArticleQuery, ArticleResponse, CategoryCount, SlugMatch};
+use crate::handlers::{knowledge_base_analytics, knowledge_base_translations};
+
+/// An article as shown to anonymous visitors, without workflow or author details.
+#[derive(Debug, Serialize)]
+pub struct HelpCenterArticle {
+    pub id: Uuid,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub category: String,
+    pub tags: Value,
+    /// Language of `title` and `content`.
+    pub locale: String,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HelpCenterListItem {
+    #[serde(flatten)]
+    pub article: HelpCenterArticle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+impl From<ArticleResponse> for HelpCenterArticle {
+    fn from(article: ArticleResponse) -> Self {
+        HelpCenterArticle {
+            id: article.id,
+            title: article.title,
+            slug: article.slug,
+            content: article.content,
+            category: article.category,
+            tags: article.tags,
+            locale: article.locale,
+            updated_at: article.updated_at,
+        }
+    }
+}
+
+impl From<ArticleListItem> for HelpCenterListItem {
+    fn from(item: ArticleListItem) -> Self {
+        HelpCenterListItem {
+            article: item.article.into(),
+            rank: item.rank,
+            snippet: item.snippet,
+        }
+    }
+}
+
+/// Live public articles, with the same search, filters, paging and translations as
+/// `/knowledge-base`.
+pub async fn list_help_center_articles(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Query(query): Query<ArticleQuery>,
+) -> Result<Json<Page<HelpCenterListItem>>, AppError> {
+    let search = query.search.clone();
+    let locale = locale::negotiate(&headers, LOCALES);
+    let page = knowledge_base::find_articles(&db, knowledge_base::visible_to(None), locale, query).await?;
+
+    if let (0, Some(search)) = (page.total, search) {
+        knowledge_base_analytics::record_search_miss(&db, &search, None).await;
+    }
+
+    Ok(Json(Page {
+        items: page.items.into_iter().map(HelpCenterListItem::from).collect(),
+        next_cursor: page.next_cursor,
+        total: page.total,
+    }))
+}
+
+/// Views are counted once a day per visitor session, which is started with a cookie on
+/// the visitor's first view. The language is picked from `Accept-Language` as for
+/// `/knowledge-base/:id`.
+pub async fn get_help_center_article(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Path(article_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    let article = KnowledgeBase::find_by_id(article_id)
+        .filter(knowledge_base::visible_to(None))
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    viewed(&db, article, &headers).await
+}
+
+/// Like `/help-center/articles/:id`; an article's earlier slugs redirect to its current one.
+pub async fn get_help_center_article_by_slug(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> Result<Response, AppError> {
+    match knowledge_base::find_by_slug(&db, &slug, knowledge_base::visible_to(None)).await? {
+        SlugMatch::Current(article) => viewed(&db, *article, &headers).await,
+        SlugMatch::Moved(slug) => {
+            Ok(Redirect::permanent(&format!("/help-center/articles/by-slug/{slug}")).into_response())
+        }
+    }
+}
+
+/// Categories of the live public articles, with how many each has.
+pub async fn list_help_center_categories(
+    State(db): State<DatabaseConnection>,
+) -> Result<Json<Vec<CategoryCount>>, AppError> {
+    Ok(Json(knowledge_base::category_counts(&db, knowledge_base::visible_to(None)).await?))
+}
+
+/// Records the view and returns the localized article. A visitor without a session
+/// gets a new one, which their first view is counted under.
+async fn viewed(
+    db: &DatabaseConnection,
+    article: <KnowledgeBase as EntityTrait>::Model,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let (session, cookie) = match knowledge_base_analytics::help_center_session(headers) {
+        Some(session) => (session, None),
+        None => {
+            let (session, cookie) = knowledge_base_analytics::new_session()?;
+            (session, Some(cookie))
+        }
+    };
+    knowledge_base_analytics::record_view(db, article.id, None, Some(session)).await;
+
+    let mut response = localized(db, article, headers).await?;
+    if let Some(cookie) = cookie {
+        response.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+
+    Ok(response)
+}
+
+async fn localized(
+    db: &DatabaseConnection,
+    article: <KnowledgeBase as EntityTrait>::Model,
+    headers: &HeaderMap,
+) -> Result<Response, DbErr> {
+    let article = HelpCenterArticle::from(knowledge_base_translations::localize(db, article, headers).await?);
+    Ok((locale::response_headers(&article.locale), Json(article)).into_response())
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set,
+    sea_query::{Expr, Func}
+};
+use serde::Deserialize;
+use std::{env, sync::Arc};
+use uuid::Uuid;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use validator::Validate;
+
+use crate::extract::{Json, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::MAX_BODY_LEN;
+use crate::models::{Comment, Ticket, User, comment, user};
+use crate::auth::STAFF_ROLES;
+use crate::email;
+use crate::handlers::comments::{self, CommentResponse};
+use crate::ws::WsState;
+
+/// Header the mail provider's webhook sends the `INBOUND_EMAIL_SECRET` in.
+const SECRET_HEADER: &str = "x-inbound-secret";
+
+/// An email parsed by the mail provider. Only replies to earlier notifications are
+/// accepted; they are matched to a comment through `In-Reply-To` or `References`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct InboundEmailRequest {
+    /// `From` header, as `jane@example.com` or `Jane Doe <jane@example.com>`.
+    #[validate(length(min = 1))]
+    pub from: String,
+    #[validate(length(min = 1, max = 998))]
+    pub message_id: String,
+    pub in_reply_to: Option<String>,
+    /// `References` header: Message-IDs separated by whitespace, oldest first.
+    pub references: Option<String>,
+    /// Plain-text body; quoted history below the reply is dropped.
+    #[validate(length(max = "MAX_BODY_LEN"))]
+    pub text: String,
+}
+
+/// Route middleware for the webhook: a request without the `INBOUND_EMAIL_SECRET` is
+/// refused before its body is read, so callers learn nothing about the payload format.
+pub async fn require_secret<B>(request: Request<B>, next: Next<B>) -> Result<Response, AppError> {
+    check_secret(request.headers())?;
+    Ok(next.run(request).await)
+}
+
+/// Adds an emailed reply to the thread as a reply to the comment it answers. The
+/// sender must be the ticket's requester or staff. Staff replies to an internal note
+/// stay internal. Redelivery of the same message returns the comment already created.
+pub async fn receive_email(
+    State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
+    ValidatedJson(payload): ValidatedJson<InboundEmailRequest>,
+) -> Result<Json<CommentResponse>, AppError> {
+    let existing = Comment::find()
+        .filter(comment::Column::EmailMessageId.eq(&payload.message_id))
+        .one(&db)
+        .await?;
+    if let Some(existing) = existing {
+        return Ok(Json(existing.into()));
+    }
+
+    let sender_email = payload
+        .from
+        .parse::<Mailbox>()
+        .map(|mailbox| mailbox.email.to_string())
+        .unwrap_or_else(|_| payload.from.trim().to_string());
+    let sender = User::find()
+        .filter(Expr::expr(Func::lower(Expr::col(user::Column::Email))).eq(sender_email.to_lowercase()))
+        .one(&db)
+        .await?
+        .ok_or(AppError::Forbidden)?;
+
+    let parent = find_replied_comment(&db, &payload).await?.ok_or_else(|| {
+        AppError::bad_request("the email is not a reply to a known comment")
+    })?;
+    let ticket = Ticket::find_by_id(parent.ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let is_staff = STAFF_ROLES.contains(&sender.role.as_str());
+    if !is_staff && (sender.id != ticket.created_by || parent.is_internal) {
+        return Err(AppError::Forbidden);
+    }
+
+    let content = strip_quoted_reply(&payload.text);
+    if content.is_empty() {
+        return Err(AppError::bad_request("the email has no reply text"));
+    }
+
+    let comment = comment::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        ticket_id: Set(ticket.id),
+        user_id: Set(sender.id),
+        content: Set(content),
+        is_internal: Set(parent.is_internal),
+        created_at: Set(Utc::now()),
+        edited_at: Set(None),
+        deleted_at: Set(None),
+        deleted_by: Set(None),
+        parent_comment_id: Set(Some(parent.id)),
+        email_message_id: Set(Some(payload.message_id)),
+    };
+
+    let comment = comment.insert(&db).await?;
+
+    comments::publish_comment(&ws, &ticket, &comment);
+    comments::notify_requester(&db, &ticket, &comment).await?;
+
+    Ok(Json(comment.into()))
+}
+
+fn check_secret(headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = env::var("INBOUND_EMAIL_SECRET").map_err(|_| AppError::Unauthorized)?;
+    let given = headers
+        .get(SECRET_HEADER)
+        .map(|value| value.as_bytes())
+        .ok_or(AppError::Unauthorized)?;
+
+    if secrets_match(given, expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Compares without returning early, so timing does not reveal how much matched.
+fn secrets_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The comment the email answers: `In-Reply-To` first, then `References` from the
+/// most recent. Each ID is either one of our notifications or an earlier inbound email.
+async fn find_replied_comment(
+    db: &DatabaseConnection,
+    payload: &InboundEmailRequest,
+) -> Result<Option<comment::Model>, AppError> {
+    let references = payload.references.as_deref().unwrap_or_default();
+    let message_ids = payload
+        .in_reply_to
+        .iter()
+        .flat_map(|ids| ids.split_whitespace())
+        .chain(references.split_whitespace().rev());
+
+    for message_id in message_ids {
+        let comment = match email::comment_id_from_message_id(message_id) {
+            Some(comment_id) => Comment::find_by_id(comment_id).one(db).await?,
+            None => {
+                Comment::find()
+                    .filter(comment::Column::EmailMessageId.eq(message_id))
+                    .one(db)
+                    .await?
+            }
+        };
+        if comment.is_some() {
+            return Ok(comment);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Keeps the text above the quoted message that mail clients append to replies.
+fn strip_quoted_reply(text: &str) -> String {
+    let reply: Vec<&str> = text
+        .lines()
+        .take_while(|line| {
+            let line = line.trim();
+            !(line.starts_with('>')
+                || (line.starts_with("On ") && line.ends_with("wrote:"))
+                || line == "-----Original Message-----")
+        })
+        .collect();
+
+    reply.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_quoted_reply_keeps_the_new_text() {
+        let text = "Thanks, that fixed it.\n\nOn Mon, 5 Feb 2024 at 10:00, Support <support@example.com> wrote:\n> Try restarting.";
+        assert_eq!(strip_quoted_reply(text), "Thanks, that fixed it.");
+
+        let text = "Still broken.\r\n-----Original Message-----\r\nFrom: Support";
+        assert_eq!(strip_quoted_reply(text), "Still broken.");
+    }
+
+    #[test]
+    fn strip_quoted_reply_is_empty_for_quote_only_replies() {
+        assert_eq!(strip_quoted_reply("> Try restarting.\n> Thanks"), "");
+    }
+}
\ No newline at end of file
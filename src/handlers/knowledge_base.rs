@@ -1,213 +1,1315 @@
 use axum::{
-    extract::{Path, Query, State, Extension},
-    http::StatusCode,
-    Json,
+    extract::{State, Extension},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
 };
 use sea_orm::{
     DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder,
-    Condition, ModelTrait, QuerySelect
+    Condition, ModelTrait, QuerySelect, PaginatorTrait, FromQueryResult, TransactionTrait, ConnectionTrait,
+    DbErr, QueryResult, JoinType, Select, sea_query::{Alias, Expr}
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
 use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+use validator::Validate;
 
-use crate::models::{KnowledgeBase, knowledge_base};
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::{is_unique_violation, AppError};
+use crate::locale::{self, DEFAULT_LOCALE};
+use crate::validation::{self, LOCALES, MAX_BODY_LEN, MAX_CATEGORY_LEN, MAX_SLUG_LEN, MAX_SUBJECT_LEN, MAX_TITLE_LEN};
+use crate::models::{
+    KnowledgeBase, KnowledgeBaseRevision, KnowledgeBaseSlugRedirect, Ticket, User, knowledge_base,
+    knowledge_base_revision, knowledge_base_slug_redirect, knowledge_base_translation, user
+};
+use crate::pagination::{self, Keyset, Page, PageRequest, parse_sort};
 use crate::auth::Claims;
+use crate::handlers::{knowledge_base_analytics, knowledge_base_translations};
 
-#[derive(Debug, Deserialize)]
+/// Lists show an article's published translation into the caller's preferred language,
+/// joined under this name, in place of the article's own text.
+const TRANSLATION: &str = "translation";
+const SHOWN_TITLE: &str = "COALESCE(translation.title, knowledge_base.title)";
+/// Articles whose own or a translation's title is similar to `$1`. Each side can use its
+/// trigram index, which a condition on `SHOWN_TITLE` cannot.
+const FUZZY_CANDIDATES: &str = "knowledge_base.id IN (\
+    SELECT id FROM knowledge_base WHERE $1 <% title \
+    UNION SELECT article_id FROM knowledge_base_translations WHERE $1 <% title)";
+const SEARCH_DOCUMENT: &str = "COALESCE(translation.search_vector, knowledge_base.search_vector)";
+const SEARCH_CONFIG: &str = "knowledge_base_search_config(COALESCE(translation.locale, knowledge_base.locale))";
+const SEARCH_QUERY: &str =
+    "websearch_to_tsquery(knowledge_base_search_config(COALESCE(translation.locale, knowledge_base.locale)), $1)";
+const HEADLINE_OPTIONS: &str = "'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=25, MinWords=8'";
+/// Any of the words in `$1`, so long ticket text still matches articles that cover
+/// part of it. NULL when `$1` has no searchable words.
+const SUGGEST_QUERY: &str = "knowledge_base_any_word_query(knowledge_base_search_config(knowledge_base.locale), $1)";
+const DEFAULT_SUGGESTIONS: u64 = 5;
+const MAX_SUGGESTIONS: u64 = 20;
+
+/// Published and inside its publishing window.
+pub(crate) const LIVE: &str = "knowledge_base.status = 'published' \
+    AND (knowledge_base.publish_at IS NULL OR knowledge_base.publish_at <= NOW()) \
+    AND (knowledge_base.unpublish_at IS NULL OR knowledge_base.unpublish_at > NOW())";
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateArticleRequest {
+    #[validate(length(min = 1, max = "MAX_TITLE_LEN"), custom = "validation::not_blank")]
     pub title: String,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
     pub content: String,
+    #[validate(length(min = 1, max = "MAX_CATEGORY_LEN"), custom = "validation::not_blank")]
     pub category: String,
+    #[validate(custom = "validation::tags")]
     pub tags: Vec<String>,
+    /// Defaults to `customers`.
+    #[validate(custom = "validation::article_visibility")]
+    pub visibility: Option<String>,
+    /// Older form of `visibility`: `true` is `public`, `false` is `customers`.
+    pub is_public: Option<bool>,
+    /// Defaults to one made from the title.
+    #[validate(custom = "validation::slug")]
+    pub slug: Option<String>,
+    /// Language the article is written in. Defaults to `en`.
+    #[validate(custom = "validation::locale")]
+    pub locale: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateArticleRequest {
+    #[validate(length(min = 1, max = "MAX_TITLE_LEN"), custom = "validation::not_blank")]
     pub title: Option<String>,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
     pub content: Option<String>,
+    #[validate(length(min = 1, max = "MAX_CATEGORY_LEN"), custom = "validation::not_blank")]
     pub category: Option<String>,
+    #[validate(custom = "validation::tags")]
     pub tags: Option<Vec<String>>,
+    #[validate(custom = "validation::article_visibility")]
+    pub visibility: Option<String>,
+    pub is_public: Option<bool>,
+    /// Defaults to one made from the new title when the title changes. The old slug
+    /// keeps redirecting to the article.
+    #[validate(custom = "validation::slug")]
+    pub slug: Option<String>,
+    #[validate(custom = "validation::locale")]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ArticleStatusRequest {
+    #[validate(custom = "validation::article_status")]
+    pub status: String,
+    /// KB editor asked to review the article; only when moving to `in_review`.
+    pub reviewer_id: Option<Uuid>,
+    /// Only when publishing; the article goes live at this time instead of now.
+    pub publish_at: Option<chrono::DateTime<Utc>>,
+    /// Only when publishing; the article is taken down at this time.
+    pub unpublish_at: Option<chrono::DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ArticleQuery {
     pub category: Option<String>,
+    pub status: Option<String>,
+    pub reviewer_id: Option<Uuid>,
     pub search: Option<String>,
+    /// `created_at`, `updated_at`, `title` or `relevance`, prefixed with `-` for descending.
+    pub sort: Option<String>,
+    pub cursor: Option<String>,
     pub page: Option<u64>,
     pub limit: Option<u64>,
 }
 
+/// What the customer has typed so far in the new ticket form.
+#[derive(Debug, Deserialize, Validate)]
+pub struct SuggestArticlesRequest {
+    #[validate(length(max = "MAX_SUBJECT_LEN"))]
+    pub subject: String,
+    #[validate(length(max = "MAX_BODY_LEN"))]
+    pub description: Option<String>,
+    #[validate(custom = "validation::tags")]
+    pub tags: Option<Vec<String>>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionQuery {
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    /// Defaults to the current revision.
+    pub to: Option<i32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ArticleResponse {
     pub id: Uuid,
     pub title: String,
+    pub slug: String,
     pub content: String,
     pub category: String,
     pub tags: Value,
+    pub revision: i32,
+    pub status: String,
+    pub reviewer_id: Option<Uuid>,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<chrono::DateTime<Utc>>,
+    pub publish_at: Option<chrono::DateTime<Utc>>,
+    pub unpublish_at: Option<chrono::DateTime<Utc>>,
+    pub visibility: String,
+    /// Whether `visibility` is `public`.
+    pub is_public: bool,
+    /// Language of `title` and `content`: the article's own, or that of a translation
+    /// picked from `Accept-Language`.
+    pub locale: String,
+    /// Language the article is written in.
+    pub source_locale: String,
+    /// Only when a translation is shown: whether the article has changed since it was
+    /// translated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation_outdated: Option<bool>,
     pub created_by: Uuid,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RevisionResponse {
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    pub category: String,
+    pub tags: Value,
+    pub edited_by: Uuid,
+    pub restored_from: Option<i32>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionSummary {
+    pub revision: i32,
+    pub title: String,
+    pub edited_by: Uuid,
+    pub restored_from: Option<i32>,
+    /// Lines added and removed compared with the previous revision.
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionDiffResponse {
+    pub from: i32,
+    pub to: i32,
+    /// Unified diff of the title, category, tags and content.
+    pub diff: String,
+}
+
+impl From<knowledge_base::Model> for ArticleResponse {
+    fn from(article: knowledge_base::Model) -> Self {
+        ArticleResponse {
+            id: article.id,
+            title: article.title,
+            slug: article.slug,
+            content: article.content,
+            category: article.category,
+            tags: article.tags,
+            revision: article.revision,
+            status: article.status,
+            reviewer_id: article.reviewer_id,
+            approved_by: article.approved_by,
+            approved_at: article.approved_at,
+            publish_at: article.publish_at,
+            unpublish_at: article.unpublish_at,
+            is_public: article.visibility == "public",
+            visibility: article.visibility,
+            locale: article.locale.clone(),
+            source_locale: article.locale,
+            translation_outdated: None,
+            created_by: article.created_by,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+        }
+    }
+}
+
+impl ArticleResponse {
+    /// Shows a translation's title and content in place of the article's own.
+    pub(crate) fn translated(self, translation: TranslatedText) -> Self {
+        ArticleResponse {
+            title: translation.title,
+            content: translation.content,
+            locale: translation.locale,
+            translation_outdated: Some(translation.outdated),
+            ..self
+        }
+    }
+}
+
+impl From<knowledge_base_revision::Model> for RevisionResponse {
+    fn from(revision: knowledge_base_revision::Model) -> Self {
+        RevisionResponse {
+            revision: revision.revision,
+            title: revision.title,
+            content: revision.content,
+            category: revision.category,
+            tags: revision.tags,
+            edited_by: revision.edited_by,
+            restored_from: revision.restored_from,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArticleListItem {
+    #[serde(flatten)]
+    pub article: ArticleResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct CategoryCount {
+    pub category: String,
+    pub articles: i64,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct SuggestedArticle {
+    pub id: Uuid,
+    pub title: String,
+    pub category: String,
+    pub tags: Value,
+    pub snippet: String,
+    pub rank: f32,
+}
+
+/// The parts of a translation shown in place of the article's own.
+#[derive(Debug)]
+pub(crate) struct TranslatedText {
+    pub locale: String,
+    pub title: String,
+    pub content: String,
+    pub outdated: bool,
+}
+
+#[derive(Debug)]
+struct ArticleRow {
+    article: knowledge_base::Model,
+    translation: Option<TranslatedText>,
+    search_rank: Option<f32>,
+    snippet: Option<String>,
+    sort_key: String,
+}
+
+impl FromQueryResult for ArticleRow {
+    fn from_query_result(row: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        let translation = match row.try_get::<Option<String>>(pre, "translation_locale")? {
+            Some(locale) => Some(TranslatedText {
+                locale,
+                title: row.try_get(pre, "translation_title")?,
+                content: row.try_get(pre, "translation_content")?,
+                outdated: row.try_get(pre, "translation_outdated")?,
+            }),
+            None => None,
+        };
+
+        Ok(ArticleRow {
+            article: knowledge_base::Model::from_query_result(row, pre)?,
+            translation,
+            search_rank: row.try_get(pre, "search_rank")?,
+            snippet: row.try_get(pre, "snippet")?,
+            sort_key: row.try_get(pre, "sort_key")?,
+        })
+    }
+}
+
+/// New articles start as drafts.
 pub async fn create_article(
     State(db): State<DatabaseConnection>,
-    Json(payload): Json<CreateArticleRequest>,
-) -> Result<Json<ArticleResponse>, StatusCode> {
-    let user_id = uuid::Uuid::new_v4();
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreateArticleRequest>,
+) -> Result<Json<ArticleResponse>, AppError> {
+    claims.require_staff()?;
+
+    let slug = match payload.slug {
+        Some(slug) => {
+            check_slug_free(&db, &slug, None).await?;
+            slug
+        }
+        None => unique_slug(&db, &slugify(&payload.title), None).await?,
+    };
+
     let article_id = Uuid::new_v4();
     let now = Utc::now();
 
-    let tags_json = serde_json::to_value(payload.tags).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let tags_json = serde_json::to_value(payload.tags)?;
 
     let article = knowledge_base::ActiveModel {
         id: Set(article_id),
         title: Set(payload.title),
+        slug: Set(slug.clone()),
         content: Set(payload.content),
         category: Set(payload.category),
         tags: Set(tags_json),
-        created_by: Set(user_id),
+        revision: Set(1),
+        status: Set("draft".to_string()),
+        visibility: Set(
+            requested_visibility(payload.visibility, payload.is_public).unwrap_or_else(|| "customers".to_string()),
+        ),
+        reviewer_id: Set(None),
+        approved_by: Set(None),
+        approved_at: Set(None),
+        publish_at: Set(None),
+        unpublish_at: Set(None),
+        locale: Set(payload.locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string())),
+        created_by: Set(claims.user_id()),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
-    let article = article.insert(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let txn = db.begin().await?;
+    claim_slug(&txn, &slug).await?;
+    let article = article.insert(&txn).await.map_err(slug_conflict(&slug))?;
+    record_revision(&txn, &article, claims.user_id(), None).await?;
+    txn.commit().await?;
 
-    Ok(Json(ArticleResponse {
-        id: article.id,
-        title: article.title,
-        content: article.content,
-        category: article.category,
-        tags: article.tags,
-        created_by: article.created_by,
-        created_at: article.created_at,
-        updated_at: article.updated_at,
-    }))
+    Ok(Json(article.into()))
 }
 
+/// In the language picked from `Accept-Language` out of the article's own and those of
+/// its published translations.
 pub async fn get_article(
     State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(article_id): Path<Uuid>,
-) -> Result<Json<ArticleResponse>, StatusCode> {
+) -> Result<Response, AppError> {
     let article = KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(Some(&claims)))
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or(AppError::NotFound)?;
 
-    Ok(Json(ArticleResponse {
-        id: article.id,
-        title: article.title,
-        content: article.content,
-        category: article.category,
-        tags: article.tags,
-        created_by: article.created_by,
-        created_at: article.created_at,
-        updated_at: article.updated_at,
-    }))
+    knowledge_base_analytics::record_view(&db, article.id, Some(claims.user_id()), None).await;
+    let article = knowledge_base_translations::localize(&db, article, &headers).await?;
+
+    Ok((locale::response_headers(&article.locale), Json(article)).into_response())
+}
+
+/// Like `GET /knowledge-base/:id`. A slug the article had before it was renamed
+/// redirects to its current one.
+pub async fn get_article_by_slug(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> Result<Response, AppError> {
+    match find_by_slug(&db, &slug, visible_to(Some(&claims))).await? {
+        SlugMatch::Current(article) => {
+            knowledge_base_analytics::record_view(&db, article.id, Some(claims.user_id()), None).await;
+            let article = knowledge_base_translations::localize(&db, *article, &headers).await?;
+            Ok((locale::response_headers(&article.locale), Json(article)).into_response())
+        }
+        SlugMatch::Moved(slug) => Ok(Redirect::permanent(&format!("/knowledge-base/by-slug/{slug}")).into_response()),
+    }
 }
 
+/// Saves changes to the title, content, category or tags as a new revision. A request
+/// that changes none of them does not create one. Only KB editors may change published or archived articles.
+///
+/// A new title also gives the article a new slug unless one is given; either way the
+/// old slug redirects to the article. A new title or content flags the article's
+/// translations as outdated.
 pub async fn update_article(
     State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
     Path(article_id): Path<Uuid>,
-    Json(payload): Json<UpdateArticleRequest>,
-) -> Result<Json<ArticleResponse>, StatusCode> {
+    ValidatedJson(payload): ValidatedJson<UpdateArticleRequest>,
+) -> Result<Json<ArticleResponse>, AppError> {
+    let txn = db.begin().await?;
+
+    // Locked so concurrent edits get consecutive revision numbers.
     let article = KnowledgeBase::find_by_id(article_id)
-        .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    check_editable(&article, &claims)?;
 
+    let title = payload.title.filter(|title| *title != article.title);
+    let content = payload.content.filter(|content| *content != article.content);
+    let category = payload.category.filter(|category| *category != article.category);
+    let tags = payload
+        .tags
+        .map(serde_json::to_value)
+        .transpose()?
+        .filter(|tags| *tags != article.tags);
+
+    let visibility = requested_visibility(payload.visibility, payload.is_public)
+        .filter(|visibility| *visibility != article.visibility);
+    let locale = payload.locale.filter(|locale| *locale != article.locale);
+    if let Some(locale) = &locale {
+        knowledge_base_translations::check_locale_free(&txn, article.id, locale).await?;
+    }
+
+    let slug = match (payload.slug, &title) {
+        (Some(slug), _) if slug != article.slug => {
+            check_slug_free(&txn, &slug, Some(article.id)).await?;
+            Some(slug)
+        }
+        (None, Some(title)) => Some(slug_for_title(&txn, &article, title).await?),
+        _ => None,
+    }
+    .filter(|slug| *slug != article.slug);
+
+    let text_changed = title.is_some() || content.is_some();
+    let content_changed = text_changed || category.is_some() || tags.is_some();
+    if !content_changed && visibility.is_none() && slug.is_none() && locale.is_none() {
+        return Ok(Json(article.into()));
+    }
+
+    let revision = article.revision;
+    let old_slug = article.slug.clone();
     let mut article: knowledge_base::ActiveModel = article.into();
-    
-    if let Some(title) = payload.title {
+
+    if let Some(title) = title {
         article.title = Set(title);
     }
-    if let Some(content) = payload.content {
+    if let Some(content) = content {
         article.content = Set(content);
     }
-    if let Some(category) = payload.category {
+    if let Some(category) = category {
         article.category = Set(category);
     }
-    if let Some(tags) = payload.tags {
-        let tags_json = serde_json::to_value(tags).map_err(|_| StatusCode::BAD_REQUEST)?;
-        article.tags = Set(tags_json);
+    if let Some(tags) = tags {
+        article.tags = Set(tags);
+    }
+    // Visibility, the slug and the locale are not part of the revision history.
+    if let Some(visibility) = visibility {
+        article.visibility = Set(visibility);
+    }
+    if let Some(locale) = locale {
+        article.locale = Set(locale);
+    }
+    if let Some(slug) = &slug {
+        change_slug(&txn, article_id, &old_slug, slug).await?;
+        article.slug = Set(slug.clone());
+    }
+
+    if content_changed {
+        article.revision = Set(revision + 1);
     }
-    
     article.updated_at = Set(Utc::now());
 
-    let article = article.update(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let article = article.update(&txn).await.map_err(slug_conflict(slug.as_deref().unwrap_or(&old_slug)))?;
+    if content_changed {
+        record_revision(&txn, &article, claims.user_id(), None).await?;
+    }
+    if text_changed {
+        knowledge_base_translations::mark_outdated(&txn, article.id).await?;
+    }
+    txn.commit().await?;
 
-    Ok(Json(ArticleResponse {
-        id: article.id,
-        title: article.title,
-        content: article.content,
-        category: article.category,
-        tags: article.tags,
-        created_by: article.created_by,
-        created_at: article.created_at,
-        updated_at: article.updated_at,
-    }))
+    Ok(Json(article.into()))
 }
 
 pub async fn delete_article(
     State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
     Path(article_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, AppError> {
     let article = KnowledgeBase::find_by_id(article_id)
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or(AppError::NotFound)?;
+    check_editable(&article, &claims)?;
 
-    article.delete(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    article.delete(&db).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Articles the caller may see: see [`visible_to`]. Each is shown and searched in its
+/// published translation into the caller's preferred language, if it has one.
 pub async fn list_articles(
     State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Query(query): Query<ArticleQuery>,
-) -> Result<Json<Vec<ArticleResponse>>, StatusCode> {
-    let mut condition = Condition::all();
+) -> Result<Json<Page<ArticleListItem>>, AppError> {
+    let search = query.search.clone();
+    let locale = locale::negotiate(&headers, LOCALES);
+    let page = find_articles(&db, visible_to(Some(&claims)), locale, query).await?;
+
+    if let (0, Some(search)) = (page.total, search) {
+        knowledge_base_analytics::record_search_miss(&db, &search, Some(claims.user_id())).await;
+    }
+
+    Ok(Json(page))
+}
+
+/// Categories of the articles the caller may see, with how many articles each has.
+pub async fn list_categories(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<CategoryCount>>, AppError> {
+    Ok(Json(category_counts(&db, visible_to(Some(&claims))).await?))
+}
+
+/// Searches, filters and pages the articles matching `visible`, in their translations
+/// into `locale` where there are any.
+pub(crate) async fn find_articles(
+    db: &DatabaseConnection,
+    visible: Condition,
+    locale: Option<&str>,
+    query: ArticleQuery,
+) -> Result<Page<ArticleListItem>, AppError> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.page, query.limit)?;
+    let mut condition = Condition::all().add(visible);
 
     if let Some(category) = query.category {
         condition = condition.add(knowledge_base::Column::Category.eq(category));
     }
-    if let Some(search) = query.search {
-        condition = condition.add(
-            knowledge_base::Column::Title.contains(&search)
-                .or(knowledge_base::Column::Content.contains(&search))
-        );
+    if let Some(status) = query.status {
+        condition = condition.add(knowledge_base::Column::Status.eq(status));
+    }
+    if let Some(reviewer_id) = query.reviewer_id {
+        condition = condition.add(knowledge_base::Column::ReviewerId.eq(reviewer_id));
+    }
+
+    let search = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty());
+
+    // The relevance expression depends on which kind of match produced the rows.
+    let (select, total, relevance) = match search {
+        Some(search) => {
+            let matched = condition
+                .clone()
+                .add(Expr::cust_with_values(format!("{SEARCH_DOCUMENT} @@ {SEARCH_QUERY}"), [search]));
+            let total = with_translation(KnowledgeBase::find(), locale)
+                .filter(matched.clone())
+                .count(db)
+                .await?;
+
+            if total > 0 {
+                let rank = format!("ts_rank({SEARCH_DOCUMENT}, {SEARCH_QUERY})");
+                let headline = format!(
+                    "ts_headline({SEARCH_CONFIG}, COALESCE(translation.content, knowledge_base.content), \
+                        {SEARCH_QUERY}, {HEADLINE_OPTIONS})"
+                );
+                let select = with_translation(KnowledgeBase::find(), locale)
+                    .filter(matched)
+                    .column_as(Expr::cust_with_values(rank.clone(), [search]), "search_rank")
+                    .column_as(Expr::cust_with_values(headline, [search]), "snippet");
+                (select, total, Some((rank, search)))
+            } else {
+                // Nothing matched the stemmed query, most likely a typo: fall back to
+                // trigram word similarity against the titles shown.
+                let fuzzy = condition.add(Expr::cust_with_values(
+                    format!("{FUZZY_CANDIDATES} AND $1 <% {SHOWN_TITLE}"),
+                    [search],
+                ));
+                let total = with_translation(KnowledgeBase::find(), locale)
+                    .filter(fuzzy.clone())
+                    .count(db)
+                    .await?;
+                let rank = format!("word_similarity($1, {SHOWN_TITLE})");
+                let select = with_translation(KnowledgeBase::find(), locale)
+                    .filter(fuzzy)
+                    .column_as(Expr::cust_with_values(rank.clone(), [search]), "search_rank")
+                    .column_as(Expr::cust("NULL::text"), "snippet");
+                (select, total, Some((rank, search)))
+            }
+        }
+        None => {
+            let total = with_translation(KnowledgeBase::find(), locale)
+                .filter(condition.clone())
+                .count(db)
+                .await?;
+            let select = with_translation(KnowledgeBase::find(), locale)
+                .filter(condition)
+                .column_as(Expr::cust("NULL::real"), "search_rank")
+                .column_as(Expr::cust("NULL::text"), "snippet");
+            (select, total, None)
+        }
+    };
+
+    let sort = query
+        .sort
+        .as_deref()
+        .unwrap_or(if relevance.is_some() { "-relevance" } else { "-created_at" });
+    let (field, descending) = parse_sort(sort);
+    let (sort, expr, values, cast) = match (field, relevance) {
+        ("relevance", Some((rank, search))) => ("relevance", rank, vec![search.into()], "real"),
+        ("relevance" | "created_at", _) => {
+            ("created_at", "knowledge_base.created_at".to_string(), vec![], "timestamptz")
+        }
+        ("updated_at", _) => ("updated_at", "knowledge_base.updated_at".to_string(), vec![], "timestamptz"),
+        ("title", _) => ("title", SHOWN_TITLE.to_string(), vec![], "text"),
+        _ => return Err(AppError::bad_request(format!("unknown sort field `{field}`"))),
+    };
+    let keyset = Keyset {
+        sort,
+        expr,
+        values,
+        cast,
+        id_column: "knowledge_base.id",
+        descending,
+    };
+
+    let articles = keyset
+        .apply(select, page.cursor.as_ref())?
+        .offset(page.offset)
+        .limit(page.limit + 1)
+        .into_model::<ArticleRow>()
+        .all(db)
+        .await?;
+
+    Ok(pagination::into_page(
+        articles,
+        &page,
+        total,
+        |row| keyset.cursor(&row.sort_key, row.article.id),
+        |row| {
+            let article = ArticleResponse::from(row.article);
+            ArticleListItem {
+                article: match row.translation {
+                    Some(translation) => article.translated(translation),
+                    None => article,
+                },
+                rank: row.search_rank,
+                snippet: row.snippet,
+            }
+        },
+    ))
+}
+
+/// Live articles related to the ticket's subject, description and tags, best first.
+/// Customers can only ask about their own tickets.
+pub async fn get_suggested_articles(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket_id): Path<Uuid>,
+    Query(query): Query<SuggestionQuery>,
+) -> Result<Json<Vec<SuggestedArticle>>, AppError> {
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !claims.is_staff() && ticket.created_by != claims.user_id() {
+        return Err(AppError::Forbidden);
     }
 
-    let page = query.page.unwrap_or(1);
-    let limit = query.limit.unwrap_or(50);
-    let offset = (page - 1) * limit;
+    let tags: Vec<String> = serde_json::from_value(ticket.tags).unwrap_or_default();
+    let text = format!("{} {} {}", ticket.subject, ticket.description, tags.join(" "));
+
+    Ok(Json(suggest(&db, &claims, &text, query.limit).await?))
+}
+
+/// Articles that may answer a question before it becomes a ticket.
+pub async fn suggest_articles(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SuggestArticlesRequest>,
+) -> Result<Json<Vec<SuggestedArticle>>, AppError> {
+    let text = format!(
+        "{} {} {}",
+        payload.subject,
+        payload.description.unwrap_or_default(),
+        payload.tags.unwrap_or_default().join(" "),
+    );
+
+    Ok(Json(suggest(&db, &claims, &text, payload.limit).await?))
+}
+
+/// Drafts are never suggested, even to the people who can see them.
+async fn suggest(
+    db: &DatabaseConnection,
+    claims: &Claims,
+    text: &str,
+    limit: Option<u64>,
+) -> Result<Vec<SuggestedArticle>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_SUGGESTIONS).clamp(1, MAX_SUGGESTIONS);
+    let rank = format!("ts_rank(knowledge_base.search_vector, {SUGGEST_QUERY})");
 
     let articles = KnowledgeBase::find()
-        .filter(condition)
-        .order_by_desc(knowledge_base::Column::CreatedAt)
-        .offset(offset)
+        .select_only()
+        .columns([
+            knowledge_base::Column::Id,
+            knowledge_base::Column::Title,
+            knowledge_base::Column::Category,
+            knowledge_base::Column::Tags,
+        ])
+        .column_as(
+            Expr::cust_with_values(
+                format!(
+                    "ts_headline(knowledge_base_search_config(knowledge_base.locale), knowledge_base.content, \
+                        {SUGGEST_QUERY}, {HEADLINE_OPTIONS})"
+                ),
+                [text],
+            ),
+            "snippet",
+        )
+        .column_as(Expr::cust_with_values(rank.clone(), [text]), "rank")
+        .filter(Expr::cust(LIVE))
+        .filter(visible_to(Some(claims)))
+        .filter(Expr::cust_with_values(format!("knowledge_base.search_vector @@ {SUGGEST_QUERY}"), [text]))
+        .order_by_desc(Expr::cust_with_values(rank, [text]))
+        .order_by_asc(knowledge_base::Column::Id)
         .limit(limit)
+        .into_model::<SuggestedArticle>()
+        .all(db)
+        .await?;
+
+    Ok(articles)
+}
+
+/// Newest first.
+pub async fn list_revisions(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+) -> Result<Json<Vec<RevisionSummary>>, AppError> {
+    claims.require_staff()?;
+
+    let revisions = KnowledgeBaseRevision::find()
+        .filter(knowledge_base_revision::Column::ArticleId.eq(article_id))
+        .order_by_asc(knowledge_base_revision::Column::Revision)
         .all(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
+    if revisions.is_empty() {
+        return Err(AppError::NotFound);
+    }
 
-    let responses: Vec<ArticleResponse> = articles
-        .into_iter()
-        .map(|article| ArticleResponse {
-            id: article.id,
-            title: article.title,
-            content: article.content,
-            category: article.category,
-            tags: article.tags,
-            created_by: article.created_by,
-            created_at: article.created_at,
-            updated_at: article.updated_at,
+    let mut summaries: Vec<RevisionSummary> = revisions
+        .iter()
+        .enumerate()
+        .map(|(index, revision)| {
+            let previous = index.checked_sub(1).map(|previous| document(&revisions[previous]));
+            let diff = TextDiff::from_lines(previous.as_deref().unwrap_or_default(), &document(revision))
+                .iter_all_changes()
+                .fold((0, 0), |(added, removed), change| match change.tag() {
+                    ChangeTag::Insert => (added + 1, removed),
+                    ChangeTag::Delete => (added, removed + 1),
+                    ChangeTag::Equal => (added, removed),
+                });
+            RevisionSummary {
+                revision: revision.revision,
+                title: revision.title.clone(),
+                edited_by: revision.edited_by,
+                restored_from: revision.restored_from,
+                lines_added: diff.0,
+                lines_removed: diff.1,
+                created_at: revision.created_at,
+            }
         })
         .collect();
+    summaries.reverse();
+
+    Ok(Json(summaries))
+}
+
+pub async fn get_revision(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((article_id, revision)): Path<(Uuid, i32)>,
+) -> Result<Json<RevisionResponse>, AppError> {
+    claims.require_staff()?;
+
+    let revision = find_revision(&db, article_id, revision).await?;
+
+    Ok(Json(revision.into()))
+}
+
+/// Unified diff from one revision to another, in either direction.
+pub async fn diff_revisions(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<Json<RevisionDiffResponse>, AppError> {
+    claims.require_staff()?;
+
+    let to = match query.to {
+        Some(to) => to,
+        None => {
+            KnowledgeBase::find_by_id(article_id)
+                .one(&db)
+                .await?
+                .ok_or(AppError::NotFound)?
+                .revision
+        }
+    };
+    let old = find_revision(&db, article_id, query.from).await?;
+    let new = find_revision(&db, article_id, to).await?;
+
+    let diff = TextDiff::from_lines(&document(&old), &document(&new))
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("revision {}", old.revision), &format!("revision {}", new.revision))
+        .to_string();
+
+    Ok(Json(RevisionDiffResponse { from: old.revision, to: new.revision, diff }))
+}
+
+/// Rolls the article back by saving a copy of an earlier revision as the newest one,
+/// so the history in between is kept.
+pub async fn restore_revision(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((article_id, revision)): Path<(Uuid, i32)>,
+) -> Result<Json<ArticleResponse>, AppError> {
+    claims.require_staff()?;
+
+    let txn = db.begin().await?;
+
+    let article = KnowledgeBase::find_by_id(article_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    check_editable(&article, &claims)?;
+    let restored = find_revision(&txn, article_id, revision).await?;
+    let current = article.revision;
+    let text_changed = restored.title != article.title || restored.content != article.content;
+
+    let slug = if restored.title != article.title {
+        Some(slug_for_title(&txn, &article, &restored.title).await?).filter(|slug| *slug != article.slug)
+    } else {
+        None
+    };
+    if let Some(slug) = &slug {
+        change_slug(&txn, article_id, &article.slug, slug).await?;
+    }
 
-    Ok(Json(responses))
-} 
\ No newline at end of file
+    let old_slug = article.slug.clone();
+    let mut article: knowledge_base::ActiveModel = article.into();
+    if let Some(slug) = &slug {
+        article.slug = Set(slug.clone());
+    }
+    article.title = Set(restored.title);
+    article.content = Set(restored.content);
+    article.category = Set(restored.category);
+    article.tags = Set(restored.tags);
+    article.revision = Set(current + 1);
+    article.updated_at = Set(Utc::now());
+
+    let article = article.update(&txn).await.map_err(slug_conflict(slug.as_deref().unwrap_or(&old_slug)))?;
+    record_revision(&txn, &article, claims.user_id(), Some(restored.revision)).await?;
+    if text_changed {
+        knowledge_base_translations::mark_outdated(&txn, article.id).await?;
+    }
+    txn.commit().await?;
+
+    Ok(Json(article.into()))
+}
+
+/// Moves an article through the workflow:
+///
+/// - `draft` → `in_review`: any staff member, optionally naming a reviewer. Sending
+///   `in_review` again reassigns the reviewer.
+/// - `in_review` → `draft`: any staff member, to withdraw or send back for changes.
+/// - `draft` or `in_review` → `published`: KB editors, who approve the article and
+///   may schedule it with `publish_at` and `unpublish_at`. Sending `published` again
+///   reschedules it.
+/// - `published` → `draft` or `archived`, `archived` → `draft` or `published`: KB editors.
+pub async fn update_article_status(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<ArticleStatusRequest>,
+) -> Result<Json<ArticleResponse>, AppError> {
+    claims.require_staff()?;
+
+    let article = KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(Some(&claims)))
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let editor_only = status_transition(&article.status, &payload.status)?;
+    if editor_only && !claims.is_kb_editor() {
+        return Err(AppError::Forbidden);
+    }
+
+    let publishing = payload.status == "published";
+    if !publishing && (payload.publish_at.is_some() || payload.unpublish_at.is_some()) {
+        return Err(AppError::bad_request("`publish_at` and `unpublish_at` only apply when publishing"));
+    }
+    if let (Some(publish_at), Some(unpublish_at)) = (payload.publish_at, payload.unpublish_at) {
+        if unpublish_at <= publish_at {
+            return Err(AppError::bad_request("`unpublish_at` must be after `publish_at`"));
+        }
+    }
+    if payload.reviewer_id.is_some() && payload.status != "in_review" {
+        return Err(AppError::bad_request("a reviewer can only be assigned when moving to `in_review`"));
+    }
+    if let Some(reviewer_id) = payload.reviewer_id {
+        let is_editor = User::find_by_id(reviewer_id)
+            .filter(user::Column::Role.is_in(["kb_editor", "admin"]))
+            .one(&db)
+            .await?
+            .is_some();
+        if !is_editor {
+            return Err(AppError::bad_request("the reviewer must be a KB editor or admin"));
+        }
+    }
+
+    let now = Utc::now();
+    let was_published = article.status == "published";
+    let mut article: knowledge_base::ActiveModel = article.into();
+
+    match payload.status.as_str() {
+        "in_review" => {
+            article.reviewer_id = Set(payload.reviewer_id);
+        }
+        "published" => {
+            if !was_published {
+                article.approved_by = Set(Some(claims.user_id()));
+                article.approved_at = Set(Some(now));
+            }
+            article.publish_at = Set(payload.publish_at);
+            article.unpublish_at = Set(payload.unpublish_at);
+        }
+        _ => {
+            article.approved_by = Set(None);
+            article.approved_at = Set(None);
+            article.publish_at = Set(None);
+            article.unpublish_at = Set(None);
+        }
+    }
+    article.status = Set(payload.status);
+    article.updated_at = Set(now);
+
+    let article = article.update(&db).await?;
+
+    Ok(Json(article.into()))
+}
+
+/// KB editors see every article. Other staff see live articles of any visibility plus
+/// those they wrote or were asked to review. Customers see live `public` and
+/// `customers` articles, and anonymous visitors live `public` ones.
+pub(crate) fn visible_to(claims: Option<&Claims>) -> Condition {
+    let live = Condition::all().add(Expr::cust(LIVE));
+
+    match claims {
+        Some(claims) if claims.is_kb_editor() => Condition::all(),
+        Some(claims) if claims.is_staff() => Condition::any()
+            .add(live)
+            .add(knowledge_base::Column::CreatedBy.eq(claims.user_id()))
+            .add(knowledge_base::Column::ReviewerId.eq(claims.user_id())),
+        Some(_) => live.add(knowledge_base::Column::Visibility.is_in(["public", "customers"])),
+        None => live.add(knowledge_base::Column::Visibility.eq("public")),
+    }
+}
+
+/// Left-joins each article's published translation into `locale` as `translation` and
+/// selects its text. With no locale nothing is joined, but the columns are still there.
+fn with_translation(select: Select<KnowledgeBase>, locale: Option<&str>) -> Select<KnowledgeBase> {
+    let locale = locale.map(str::to_string);
+    let relation = knowledge_base_translation::Entity::belongs_to(KnowledgeBase)
+        .from(knowledge_base_translation::Column::ArticleId)
+        .to(knowledge_base::Column::Id)
+        .on_condition(move |_, _| {
+            Condition::all()
+                .add(Expr::cust_with_values("translation.locale = $1", [locale.clone()]))
+                .add(Expr::cust("translation.status = 'published'"))
+        })
+        .into();
+
+    select
+        .join_as_rev(JoinType::LeftJoin, relation, Alias::new(TRANSLATION))
+        .column_as(Expr::cust("translation.locale"), "translation_locale")
+        .column_as(Expr::cust("translation.title"), "translation_title")
+        .column_as(Expr::cust("translation.content"), "translation_content")
+        .column_as(Expr::cust("translation.outdated"), "translation_outdated")
+}
+
+/// Whether an article may move from status `from` to `to`, and if so whether only KB
+/// editors may move it. Publishing, unpublishing and archiving are editor-only.
+pub(crate) fn status_transition(from: &str, to: &str) -> Result<bool, AppError> {
+    match (from, to) {
+        ("draft" | "in_review", "in_review") | ("in_review", "draft") => Ok(false),
+        ("draft" | "in_review" | "published" | "archived", "published")
+        | ("published", "draft" | "archived")
+        | ("archived", "draft") => Ok(true),
+        (from, to) => Err(AppError::conflict(format!("an article cannot move from `{from}` to `{to}`"))),
+    }
+}
+
+/// Lowercase words from `text` joined by hyphens, for use as a slug. Non-ASCII text is
+/// transliterated first, so `Über uns` becomes `uber-uns`.
+pub(crate) fn slugify(text: &str) -> String {
+    let slug = deunicode::deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    // Leaves room for a numeric suffix.
+    let slug = slug[..slug.len().min(MAX_SLUG_LEN - 10)].trim_end_matches('-');
+    if slug.is_empty() {
+        "article".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// `base`, or `base` with the lowest free numeric suffix when it is taken. Slugs other
+/// articles had before they were renamed count as taken, so their old links keep
+/// working; `article_id`'s own current and old slugs do not.
+pub(crate) async fn unique_slug<C: ConnectionTrait>(
+    db: &C,
+    base: &str,
+    article_id: Option<Uuid>,
+) -> Result<String, DbErr> {
+    let mut current = KnowledgeBase::find()
+        .select_only()
+        .column(knowledge_base::Column::Slug)
+        .filter(knowledge_base::Column::Slug.starts_with(base));
+    let mut old = KnowledgeBaseSlugRedirect::find()
+        .select_only()
+        .column(knowledge_base_slug_redirect::Column::Slug)
+        .filter(knowledge_base_slug_redirect::Column::Slug.starts_with(base));
+    if let Some(article_id) = article_id {
+        current = current.filter(knowledge_base::Column::Id.ne(article_id));
+        old = old.filter(knowledge_base_slug_redirect::Column::ArticleId.ne(article_id));
+    }
+
+    let mut taken: Vec<String> = current.into_tuple().all(db).await?;
+    taken.extend(old.into_tuple::<String>().all(db).await?);
+
+    let slug = std::iter::once(base.to_string())
+        .chain((2..).map(|n| format!("{base}-{n}")))
+        .find(|slug| !taken.contains(slug))
+        .unwrap_or_default();
+
+    Ok(slug)
+}
+
+/// The slug a renamed article gets from its new title.
+async fn slug_for_title<C: ConnectionTrait>(
+    db: &C,
+    article: &knowledge_base::Model,
+    title: &str,
+) -> Result<String, DbErr> {
+    unique_slug(db, &slugify(title), Some(article.id)).await
+}
+
+/// A slug asked for explicitly may only be in use by `article_id` itself. It may have
+/// been another article's old slug, which then stops redirecting there.
+async fn check_slug_free<C: ConnectionTrait>(db: &C, slug: &str, article_id: Option<Uuid>) -> Result<(), AppError> {
+    let mut select = KnowledgeBase::find().filter(knowledge_base::Column::Slug.eq(slug));
+    if let Some(article_id) = article_id {
+        select = select.filter(knowledge_base::Column::Id.ne(article_id));
+    }
+
+    if select.one(db).await?.is_some() {
+        Err(slug_in_use(slug))
+    } else {
+        Ok(())
+    }
+}
+
+fn slug_in_use(slug: &str) -> AppError {
+    AppError::conflict(format!("the slug `{slug}` is already in use"))
+}
+
+/// For the write that saves a slug. `unique_slug` and `check_slug_free` only read, so a
+/// concurrent request can take the same slug first; `knowledge_base_slug_key` then
+/// rejects this write, which is reported like a slug that was already taken.
+pub(crate) fn slug_conflict(slug: &str) -> impl FnOnce(DbErr) -> AppError + '_ {
+    move |err| if is_unique_violation(&err) { slug_in_use(slug) } else { err.into() }
+}
+
+/// Removes `slug` from the redirects before an article starts using it.
+pub(crate) async fn claim_slug<C: ConnectionTrait>(db: &C, slug: &str) -> Result<(), DbErr> {
+    KnowledgeBaseSlugRedirect::delete_many()
+        .filter(knowledge_base_slug_redirect::Column::Slug.eq(slug))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Moves the article from slug `old` to `new`, keeping `old` as a redirect.
+async fn change_slug<C: ConnectionTrait>(db: &C, article_id: Uuid, old: &str, new: &str) -> Result<(), DbErr> {
+    claim_slug(db, new).await?;
+
+    let redirect = knowledge_base_slug_redirect::ActiveModel {
+        slug: Set(old.to_string()),
+        article_id: Set(article_id),
+        created_at: Set(Utc::now()),
+    };
+    redirect.insert(db).await?;
+
+    Ok(())
+}
+
+/// What a slug refers to among the articles matching a visibility condition.
+pub(crate) enum SlugMatch {
+    Current(Box<knowledge_base::Model>),
+    /// An earlier slug; holds the article's current one.
+    Moved(String),
+}
+
+pub(crate) async fn find_by_slug(db: &DatabaseConnection, slug: &str, visible: Condition) -> Result<SlugMatch, AppError> {
+    let article = KnowledgeBase::find()
+        .filter(knowledge_base::Column::Slug.eq(slug))
+        .filter(visible.clone())
+        .one(db)
+        .await?;
+    if let Some(article) = article {
+        return Ok(SlugMatch::Current(Box::new(article)));
+    }
+
+    let current: Option<String> = KnowledgeBaseSlugRedirect::find()
+        .select_only()
+        .column(knowledge_base::Column::Slug)
+        .join(
+            JoinType::InnerJoin,
+            knowledge_base_slug_redirect::Entity::belongs_to(KnowledgeBase)
+                .from(knowledge_base_slug_redirect::Column::ArticleId)
+                .to(knowledge_base::Column::Id)
+                .into(),
+        )
+        .filter(knowledge_base_slug_redirect::Column::Slug.eq(slug))
+        .filter(visible)
+        .into_tuple()
+        .one(db)
+        .await?;
+
+    current.map(SlugMatch::Moved).ok_or(AppError::NotFound)
+}
+
+/// Article counts per category among the articles matching `visible`, by category name.
+pub(crate) async fn category_counts(db: &DatabaseConnection, visible: Condition) -> Result<Vec<CategoryCount>, DbErr> {
+    KnowledgeBase::find()
+        .select_only()
+        .column(knowledge_base::Column::Category)
+        .column_as(Expr::cust("count(*)"), "articles")
+        .filter(visible)
+        .group_by(knowledge_base::Column::Category)
+        .order_by_asc(knowledge_base::Column::Category)
+        .into_model::<CategoryCount>()
+        .all(db)
+        .await
+}
+
+/// `visibility` wins over the older `is_public` flag.
+fn requested_visibility(visibility: Option<String>, is_public: Option<bool>) -> Option<String> {
+    visibility.or_else(|| is_public.map(|is_public| if is_public { "public" } else { "customers" }.to_string()))
+}
+
+/// Staff may change drafts and articles in review; published and archived articles
+/// need a KB editor.
+fn check_editable(article: &knowledge_base::Model, claims: &Claims) -> Result<(), AppError> {
+    claims.require_staff()?;
+
+    if matches!(article.status.as_str(), "draft" | "in_review") || claims.is_kb_editor() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
+
+async fn find_revision<C: ConnectionTrait>(
+    db: &C,
+    article_id: Uuid,
+    revision: i32,
+) -> Result<knowledge_base_revision::Model, AppError> {
+    KnowledgeBaseRevision::find()
+        .filter(knowledge_base_revision::Column::ArticleId.eq(article_id))
+        .filter(knowledge_base_revision::Column::Revision.eq(revision))
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// Stores the article as it now is under its current revision number.
+pub(crate) async fn record_revision<C: ConnectionTrait>(
+    db: &C,
+    article: &knowledge_base::Model,
+    edited_by: Uuid,
+    restored_from: Option<i32>,
+) -> Result<(), DbErr> {
+    let revision = knowledge_base_revision::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        article_id: Set(article.id),
+        revision: Set(article.revision),
+        title: Set(article.title.clone()),
+        content: Set(article.content.clone()),
+        category: Set(article.category.clone()),
+        tags: Set(article.tags.clone()),
+        edited_by: Set(edited_by),
+        restored_from: Set(restored_from),
+        created_at: Set(article.updated_at),
+    };
+    revision.insert(db).await?;
+
+    Ok(())
+}
+
+/// The text a revision is diffed as: its fields as a short header, then the content.
+fn document(revision: &knowledge_base_revision::Model) -> String {
+    let tags: Vec<String> = serde_json::from_value(revision.tags.clone()).unwrap_or_default();
+    let mut document = format!(
+        "Title: {}\nCategory: {}\nTags: {}\n\n{}",
+        revision.title,
+        revision.category,
+        tags.join(", "),
+        revision.content,
+    );
+    if !document.ends_with('\n') {
+        document.push('\n');
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_makes_lowercase_ascii_slugs() {
+        assert_eq!(slugify("  How to: reset your password?! "), "how-to-reset-your-password");
+        assert_eq!(slugify("Über uns"), "uber-uns");
+        assert_eq!(slugify("Café & crème"), "cafe-creme");
+        assert_eq!(slugify("!!!"), "article");
+    }
+
+    #[test]
+    fn slugify_leaves_room_for_a_suffix() {
+        let slug = slugify(&"word ".repeat(100));
+        assert!(slug.len() <= MAX_SLUG_LEN - 10);
+        assert!(!slug.ends_with('-'));
+    }
+
+    #[test]
+    fn status_transition_follows_the_workflow() {
+        assert!(!status_transition("draft", "in_review").unwrap());
+        assert!(!status_transition("in_review", "draft").unwrap());
+        assert!(status_transition("in_review", "published").unwrap());
+        assert!(status_transition("published", "archived").unwrap());
+        assert!(status_transition("archived", "draft").unwrap());
+    }
+
+    #[test]
+    fn status_transition_rejects_skipped_steps() {
+        assert!(matches!(status_transition("draft", "archived"), Err(AppError::Conflict(_))));
+        assert!(matches!(status_transition("archived", "in_review"), Err(AppError::Conflict(_))));
+    }
+}
\ No newline at end of file
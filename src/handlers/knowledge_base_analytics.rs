@@ -0,0 +1,402 @@
+use axum::{
+    extract::{State, Extension},
+    http::{header, HeaderMap, HeaderValue},
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    FromQueryResult, JoinType, Select, sea_query::{Expr, OnConflict}
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::MAX_BODY_LEN;
+use crate::models::{
+    KnowledgeBase, KnowledgeBaseSearchMiss, KnowledgeBaseView, KnowledgeBaseVote, knowledge_base,
+    knowledge_base_search_miss, knowledge_base_view, knowledge_base_vote
+};
+use crate::auth::{self, Claims, HELP_CENTER_SESSION_DAYS};
+use crate::handlers::knowledge_base::visible_to;
+
+/// Cookie holding an anonymous help center visitor's signed session token.
+const SESSION_COOKIE: &str = "kb_session";
+const DEFAULT_REPORT_LIMIT: u64 = 10;
+const MAX_REPORT_LIMIT: u64 = 100;
+/// Articles with fewer votes are left out of the least helpful report.
+const DEFAULT_MIN_VOTES: i64 = 3;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct VoteRequest {
+    pub helpful: bool,
+    #[validate(length(max = "MAX_BODY_LEN"))]
+    pub feedback: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    pub since: Option<chrono::DateTime<Utc>>,
+    pub until: Option<chrono::DateTime<Utc>>,
+    pub limit: Option<u64>,
+    /// Least helpful report only; defaults to 3.
+    pub min_votes: Option<i64>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct VoteSummary {
+    pub helpful: i64,
+    pub not_helpful: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoteResponse {
+    pub article_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub helpful: bool,
+    pub feedback: Option<String>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ArticleViewStats {
+    pub article_id: Uuid,
+    pub title: String,
+    /// Counted once per viewer per day.
+    pub views: i64,
+    pub viewers: i64,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ArticleHelpfulnessStats {
+    pub article_id: Uuid,
+    pub title: String,
+    pub helpful: i64,
+    pub not_helpful: i64,
+    /// Share of votes that were helpful, from 0 to 1.
+    pub helpful_ratio: f64,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct FailedSearchStats {
+    pub query: String,
+    pub searches: i64,
+    pub last_searched_at: chrono::DateTime<Utc>,
+}
+
+impl From<knowledge_base_vote::Model> for VoteResponse {
+    fn from(vote: knowledge_base_vote::Model) -> Self {
+        VoteResponse {
+            article_id: vote.article_id,
+            user_id: vote.user_id,
+            helpful: vote.helpful,
+            feedback: vote.feedback,
+            updated_at: vote.updated_at,
+        }
+    }
+}
+
+/// Answers "was this helpful?" for a signed-in user. Voting again replaces the answer.
+pub async fn vote_article(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<VoteRequest>,
+) -> Result<Json<VoteSummary>, AppError> {
+    let article = KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(Some(&claims)))
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    record_vote(&db, article.id, Some(claims.user_id()), format!("user:{}", claims.user_id()), payload).await?;
+
+    Ok(Json(vote_summary(&db, article.id).await?))
+}
+
+/// Answers "was this helpful?" for an anonymous help center visitor, who is identified
+/// by the session cookie set when they viewed an article.
+pub async fn vote_help_center_article(
+    State(db): State<DatabaseConnection>,
+    headers: HeaderMap,
+    Path(article_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<VoteRequest>,
+) -> Result<Json<VoteSummary>, AppError> {
+    let session = help_center_session(&headers)
+        .ok_or_else(|| AppError::bad_request("a help center session is required to vote; view the article first"))?;
+    let article = KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(None))
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    record_vote(&db, article.id, None, format!("session:{session}"), payload).await?;
+
+    Ok(Json(vote_summary(&db, article.id).await?))
+}
+
+/// Every answer for an article, newest first, with any written feedback.
+pub async fn list_article_votes(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+) -> Result<Json<Vec<VoteResponse>>, AppError> {
+    claims.require_staff()?;
+
+    let votes = KnowledgeBaseVote::find()
+        .filter(knowledge_base_vote::Column::ArticleId.eq(article_id))
+        .order_by_desc(knowledge_base_vote::Column::UpdatedAt)
+        .all(&db)
+        .await?;
+
+    Ok(Json(votes.into_iter().map(VoteResponse::from).collect()))
+}
+
+/// Articles with the most views in the period.
+pub async fn get_most_viewed(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Vec<ArticleViewStats>>, AppError> {
+    claims.require_staff()?;
+
+    let mut select = KnowledgeBaseView::find()
+        .select_only()
+        .column(knowledge_base_view::Column::ArticleId)
+        .column(knowledge_base::Column::Title)
+        .column_as(Expr::cust("count(*)"), "views")
+        .column_as(Expr::cust("count(DISTINCT knowledge_base_views.viewer)"), "viewers")
+        .join(
+            JoinType::InnerJoin,
+            knowledge_base_view::Entity::belongs_to(KnowledgeBase)
+                .from(knowledge_base_view::Column::ArticleId)
+                .to(knowledge_base::Column::Id)
+                .into(),
+        )
+        .filter(visible_to(Some(&claims)))
+        .group_by(knowledge_base_view::Column::ArticleId)
+        .group_by(knowledge_base::Column::Title)
+        .order_by_desc(Expr::cust("count(*)"))
+        .order_by_asc(knowledge_base::Column::Title);
+
+    if let Some(since) = query.since {
+        select = select.filter(knowledge_base_view::Column::ViewedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(knowledge_base_view::Column::ViewedAt.lt(until));
+    }
+
+    let stats = limited(select, query.limit)
+        .into_model::<ArticleViewStats>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(stats))
+}
+
+/// Articles with the lowest share of helpful votes in the period, among those with at
+/// least `min_votes` votes.
+pub async fn get_least_helpful(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Vec<ArticleHelpfulnessStats>>, AppError> {
+    claims.require_staff()?;
+
+    let ratio = "(count(*) FILTER (WHERE knowledge_base_votes.helpful))::float8 / count(*)";
+    let mut select = with_vote_counts(KnowledgeBaseVote::find().select_only())
+        .column(knowledge_base_vote::Column::ArticleId)
+        .column(knowledge_base::Column::Title)
+        .column_as(Expr::cust(ratio), "helpful_ratio")
+        .join(
+            JoinType::InnerJoin,
+            knowledge_base_vote::Entity::belongs_to(KnowledgeBase)
+                .from(knowledge_base_vote::Column::ArticleId)
+                .to(knowledge_base::Column::Id)
+                .into(),
+        )
+        .filter(visible_to(Some(&claims)))
+        .group_by(knowledge_base_vote::Column::ArticleId)
+        .group_by(knowledge_base::Column::Title)
+        .having(Expr::cust_with_values("count(*) >= $1", [query.min_votes.unwrap_or(DEFAULT_MIN_VOTES)]))
+        .order_by_asc(Expr::cust(ratio))
+        .order_by_desc(Expr::cust("count(*)"))
+        .order_by_asc(knowledge_base::Column::Title);
+
+    if let Some(since) = query.since {
+        select = select.filter(knowledge_base_vote::Column::UpdatedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(knowledge_base_vote::Column::UpdatedAt.lt(until));
+    }
+
+    let stats = limited(select, query.limit)
+        .into_model::<ArticleHelpfulnessStats>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(stats))
+}
+
+/// The most frequent searches that found nothing in the period.
+pub async fn get_failed_searches(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Vec<FailedSearchStats>>, AppError> {
+    claims.require_staff()?;
+
+    let mut select = KnowledgeBaseSearchMiss::find()
+        .select_only()
+        .column(knowledge_base_search_miss::Column::Query)
+        .column_as(Expr::cust("count(*)"), "searches")
+        .column_as(knowledge_base_search_miss::Column::SearchedAt.max(), "last_searched_at")
+        .group_by(knowledge_base_search_miss::Column::Query)
+        .order_by_desc(Expr::cust("count(*)"))
+        .order_by_asc(knowledge_base_search_miss::Column::Query);
+
+    if let Some(since) = query.since {
+        select = select.filter(knowledge_base_search_miss::Column::SearchedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(knowledge_base_search_miss::Column::SearchedAt.lt(until));
+    }
+
+    let stats = limited(select, query.limit)
+        .into_model::<FailedSearchStats>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(stats))
+}
+
+/// Counts a view of the article unless this viewer already viewed it today. Signed-in
+/// users are identified by their ID, anonymous visitors by their help center session;
+/// views without either are not counted. Failures are logged rather than returned, so
+/// analytics never fail the read they are recorded for.
+pub(crate) async fn record_view(db: &DatabaseConnection, article_id: Uuid, user_id: Option<Uuid>, session: Option<Uuid>) {
+    let viewer = match (user_id, session) {
+        (Some(user_id), _) => format!("user:{user_id}"),
+        (None, Some(session)) => format!("session:{session}"),
+        (None, None) => return,
+    };
+    let now = Utc::now();
+
+    let view = knowledge_base_view::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        article_id: Set(article_id),
+        user_id: Set(user_id),
+        viewer: Set(viewer),
+        viewed_on: Set(now.date_naive()),
+        viewed_at: Set(now),
+    };
+    let inserted = KnowledgeBaseView::insert(view)
+        .on_conflict(
+            OnConflict::columns([
+                knowledge_base_view::Column::ArticleId,
+                knowledge_base_view::Column::Viewer,
+                knowledge_base_view::Column::ViewedOn,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await;
+    if let Err(e) = inserted {
+        tracing::warn!(%article_id, "article view not recorded: {e}");
+    }
+}
+
+/// Records a search that found no articles, so missing content can be written. Like
+/// views, failures are only logged.
+pub(crate) async fn record_search_miss(db: &DatabaseConnection, search: &str, user_id: Option<Uuid>) {
+    let query = search.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if query.is_empty() {
+        return;
+    }
+
+    let miss = knowledge_base_search_miss::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        query: Set(query),
+        user_id: Set(user_id),
+        searched_at: Set(Utc::now()),
+    };
+    if let Err(e) = miss.insert(db).await {
+        tracing::warn!("search miss not recorded: {e}");
+    }
+}
+
+async fn record_vote(
+    db: &DatabaseConnection,
+    article_id: Uuid,
+    user_id: Option<Uuid>,
+    voter: String,
+    payload: VoteRequest,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let vote = knowledge_base_vote::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        article_id: Set(article_id),
+        user_id: Set(user_id),
+        voter: Set(voter),
+        helpful: Set(payload.helpful),
+        feedback: Set(payload.feedback.filter(|feedback| !feedback.trim().is_empty())),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    KnowledgeBaseVote::insert(vote)
+        .on_conflict(
+            OnConflict::columns([knowledge_base_vote::Column::ArticleId, knowledge_base_vote::Column::Voter])
+                .update_columns([
+                    knowledge_base_vote::Column::Helpful,
+                    knowledge_base_vote::Column::Feedback,
+                    knowledge_base_vote::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(())
+}
+
+async fn vote_summary(db: &DatabaseConnection, article_id: Uuid) -> Result<VoteSummary, AppError> {
+    with_vote_counts(KnowledgeBaseVote::find().select_only())
+        .filter(knowledge_base_vote::Column::ArticleId.eq(article_id))
+        .into_model::<VoteSummary>()
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// The anonymous visitor's session, if the request carries a session cookie this
+/// server issued. A missing, forged or expired cookie counts as no session.
+pub(crate) fn help_center_session(headers: &HeaderMap) -> Option<Uuid> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+        .find_map(|token| auth::verify_help_center_session(token).ok())
+}
+
+/// A new help center session and the `Set-Cookie` value that starts it.
+pub(crate) fn new_session() -> Result<(Uuid, HeaderValue), AppError> {
+    let (session, token) = auth::create_help_center_session()?;
+    let max_age = HELP_CENTER_SESSION_DAYS * 24 * 60 * 60;
+    let cookie = format!("{SESSION_COOKIE}={token}; Path=/help-center; Max-Age={max_age}; HttpOnly; SameSite=Lax");
+
+    Ok((session, HeaderValue::from_str(&cookie).expect("session cookie is a valid header value")))
+}
+
+fn with_vote_counts(select: Select<KnowledgeBaseVote>) -> Select<KnowledgeBaseVote> {
+    select
+        .column_as(Expr::cust("count(*) FILTER (WHERE knowledge_base_votes.helpful)"), "helpful")
+        .column_as(Expr::cust("count(*) FILTER (WHERE NOT knowledge_base_votes.helpful)"), "not_helpful")
+}
+
+fn limited<E: EntityTrait>(select: Select<E>, limit: Option<u64>) -> Select<E> {
+    select.limit(limit.unwrap_or(DEFAULT_REPORT_LIMIT).clamp(1, MAX_REPORT_LIMIT))
+}
\ No newline at end of file
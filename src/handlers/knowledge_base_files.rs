@@ -0,0 +1,332 @@
+use axum::{
+    extract::{State, Extension},
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    TransactionTrait, ConnectionTrait
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::article_files::{self, ArticleEntry, ArticleFile};
+use crate::extract::{Json, Multipart, Query};
+use crate::error::{AppError, FieldError};
+use crate::validation;
+use crate::models::{KnowledgeBase, knowledge_base};
+use crate::auth::Claims;
+use crate::handlers::knowledge_base::{claim_slug, record_revision, slug_conflict, slugify, status_transition};
+use crate::handlers::knowledge_base_translations;
+use crate::locale::DEFAULT_LOCALE;
+
+/// Multipart field that carries the files to import; other fields are ignored.
+const FILE_FIELD: &str = "file";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    pub category: Option<String>,
+    pub status: Option<String>,
+}
+
+/// Slugs of the imported articles by what happened to them.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+/// Creates or updates articles from uploaded Markdown, JSON or tar files, matched by
+/// slug. Nothing is saved unless every file is valid.
+pub async fn import_articles(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Multipart(mut multipart): Multipart,
+) -> Result<Json<ImportSummary>, AppError> {
+    claims.require_kb_editor()?;
+
+    let mut entries = Vec::new();
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+        let name = field.file_name().unwrap_or_default().to_string();
+        let data = field.bytes().await?;
+        entries.extend(article_files::read_file(&name, &data).map_err(|e| AppError::bad_request(e.to_string()))?);
+    }
+
+    Ok(Json(import(&db, entries, claims.user_id()).await?))
+}
+
+/// Every article as a gzipped tar of Markdown files, or as a JSON array, in the format
+/// the import accepts.
+pub async fn export_articles(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, AppError> {
+    claims.require_kb_editor()?;
+
+    let articles = export(&db, query.category, query.status).await?;
+
+    match query.format.unwrap_or_default() {
+        ExportFormat::Json => Ok(Json(articles).into_response()),
+        ExportFormat::Markdown => {
+            let archive = article_files::write_archive(&articles)
+                .map_err(|e| AppError::internal(format!("failed to build the export archive: {e}")))?;
+            Ok((
+                [
+                    (header::CONTENT_TYPE, HeaderValue::from_static("application/gzip")),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        HeaderValue::from_static("attachment; filename=\"knowledge-base.tar.gz\""),
+                    ),
+                ],
+                archive,
+            )
+                .into_response())
+        }
+    }
+}
+
+/// Validates every entry, then creates or updates the articles in one transaction.
+/// Title, content, category and tag changes create a revision attributed to
+/// `user_id`. `status`, `visibility` and `locale` are only changed when the file sets
+/// them, and new articles start as `draft`, `customers` and `en` otherwise.
+pub(crate) async fn import(
+    db: &DatabaseConnection,
+    entries: Vec<ArticleEntry>,
+    user_id: Uuid,
+) -> Result<ImportSummary, AppError> {
+    if entries.is_empty() {
+        return Err(AppError::bad_request("no articles were found in the upload"));
+    }
+
+    let mut errors = Vec::new();
+    let mut paths_by_slug: HashMap<String, String> = HashMap::new();
+    let mut articles = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let slug = entry.article.slug.clone().unwrap_or_else(|| default_slug(&entry));
+        let article = ArticleFile { slug: Some(slug.clone()), ..entry.article };
+
+        if let Err(e) = article.validate() {
+            errors.extend(validation::field_errors(&e, &entry.path));
+        }
+        match paths_by_slug.get(&slug) {
+            Some(other) => errors.push(FieldError::new(
+                format!("{}.slug", entry.path),
+                "duplicate",
+                format!("`{slug}` is also used by {other}"),
+            )),
+            None => {
+                paths_by_slug.insert(slug.clone(), entry.path.clone());
+            }
+        }
+
+        articles.push((entry.path, slug, article));
+    }
+
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+
+    let mut summary = ImportSummary::default();
+    let txn = db.begin().await?;
+
+    for (path, slug, file) in articles {
+        let existing = KnowledgeBase::find()
+            .filter(knowledge_base::Column::Slug.eq(&slug))
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+
+        match existing {
+            Some(article) => {
+                // Imports change status like the workflow endpoint; the importer is a KB editor.
+                if let Some(status) = file.status.as_deref().filter(|status| *status != article.status) {
+                    if let Err(e) = status_transition(&article.status, status) {
+                        errors.push(FieldError::new(format!("{path}.status"), "invalid_transition", e.to_string()));
+                        continue;
+                    }
+                }
+                if update_from_file(&txn, article, file, user_id).await? {
+                    summary.updated.push(slug);
+                } else {
+                    summary.unchanged.push(slug);
+                }
+            }
+            None => {
+                create_from_file(&txn, slug.clone(), file, user_id).await?;
+                summary.created.push(slug);
+            }
+        }
+    }
+
+    // Dropping the transaction rolls back the articles already written.
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+    txn.commit().await?;
+
+    Ok(summary)
+}
+
+/// Articles ordered by slug, optionally only those in a category or status.
+pub(crate) async fn export(
+    db: &DatabaseConnection,
+    category: Option<String>,
+    status: Option<String>,
+) -> Result<Vec<ArticleFile>, AppError> {
+    let mut select = KnowledgeBase::find().order_by_asc(knowledge_base::Column::Slug);
+    if let Some(category) = category {
+        select = select.filter(knowledge_base::Column::Category.eq(category));
+    }
+    if let Some(status) = status {
+        select = select.filter(knowledge_base::Column::Status.eq(status));
+    }
+
+    select
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|article| -> Result<ArticleFile, AppError> {
+            Ok(ArticleFile {
+                slug: Some(article.slug),
+                title: article.title,
+                category: article.category,
+                tags: serde_json::from_value(article.tags)?,
+                visibility: Some(article.visibility),
+                status: Some(article.status),
+                locale: Some(article.locale),
+                content: article.content,
+            })
+        })
+        .collect()
+}
+
+async fn create_from_file<C: ConnectionTrait>(
+    db: &C,
+    slug: String,
+    file: ArticleFile,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let status = file.status.unwrap_or_else(|| "draft".to_string());
+    let published = status == "published";
+    // The file's slug wins over another article's old slug.
+    claim_slug(db, &slug).await?;
+
+    let article = knowledge_base::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        title: Set(file.title),
+        slug: Set(slug.clone()),
+        content: Set(file.content),
+        category: Set(file.category),
+        tags: Set(serde_json::to_value(file.tags)?),
+        revision: Set(1),
+        status: Set(status),
+        visibility: Set(file.visibility.unwrap_or_else(|| "customers".to_string())),
+        reviewer_id: Set(None),
+        approved_by: Set(published.then_some(user_id)),
+        approved_at: Set(published.then_some(now)),
+        publish_at: Set(None),
+        unpublish_at: Set(None),
+        locale: Set(file.locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string())),
+        created_by: Set(user_id),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    let article = article.insert(db).await.map_err(slug_conflict(&slug))?;
+    record_revision(db, &article, user_id, None).await?;
+
+    Ok(())
+}
+
+/// Returns whether anything changed.
+async fn update_from_file<C: ConnectionTrait>(
+    db: &C,
+    article: knowledge_base::Model,
+    file: ArticleFile,
+    user_id: Uuid,
+) -> Result<bool, AppError> {
+    let tags = serde_json::to_value(file.tags)?;
+    let text_changed = file.title != article.title || file.content != article.content;
+    let content_changed = text_changed || file.category != article.category || tags != article.tags;
+    let visibility = file.visibility.filter(|visibility| *visibility != article.visibility);
+    let status = file.status.filter(|status| *status != article.status);
+    let locale = file.locale.filter(|locale| *locale != article.locale);
+
+    if !content_changed && visibility.is_none() && status.is_none() && locale.is_none() {
+        return Ok(false);
+    }
+    if let Some(locale) = &locale {
+        knowledge_base_translations::check_locale_free(db, article.id, locale).await?;
+    }
+
+    let now = Utc::now();
+    let revision = article.revision;
+    let mut article: knowledge_base::ActiveModel = article.into();
+
+    if content_changed {
+        article.title = Set(file.title);
+        article.content = Set(file.content);
+        article.category = Set(file.category);
+        article.tags = Set(tags);
+        article.revision = Set(revision + 1);
+    }
+    if let Some(visibility) = visibility {
+        article.visibility = Set(visibility);
+    }
+    if let Some(locale) = locale {
+        article.locale = Set(locale);
+    }
+    // Same bookkeeping as a status change through the workflow endpoint.
+    if let Some(status) = status {
+        if status == "published" {
+            article.approved_by = Set(Some(user_id));
+            article.approved_at = Set(Some(now));
+        } else {
+            article.approved_by = Set(None);
+            article.approved_at = Set(None);
+            article.publish_at = Set(None);
+            article.unpublish_at = Set(None);
+        }
+        article.status = Set(status);
+    }
+    article.updated_at = Set(now);
+
+    let article = article.update(db).await?;
+    if content_changed {
+        record_revision(db, &article, user_id, None).await?;
+    }
+    if text_changed {
+        knowledge_base_translations::mark_outdated(db, article.id).await?;
+    }
+
+    Ok(true)
+}
+
+/// The file name without its extension for Markdown files, the title for JSON.
+fn default_slug(entry: &ArticleEntry) -> String {
+    if entry.is_markdown() {
+        let file_name = entry.path.rsplit(['/', '\\']).next().unwrap_or_default();
+        let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+        slugify(stem)
+    } else {
+        slugify(&entry.article.title)
+    }
+}
\ No newline at end of file
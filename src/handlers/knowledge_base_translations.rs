@@ -0,0 +1,293 @@
+use axum::{
+    extract::{State, Extension},
+    http::{HeaderMap, StatusCode},
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    ModelTrait, TransactionTrait, ConnectionTrait, DbErr, sea_query::Expr
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::extract::{Json, Path, ValidatedJson};
+use crate::error::AppError;
+use crate::locale;
+use crate::validation::{self, LOCALES, MAX_BODY_LEN, MAX_TITLE_LEN};
+use crate::models::{
+    KnowledgeBase, KnowledgeBaseRevision, KnowledgeBaseTranslation, knowledge_base, knowledge_base_revision,
+    knowledge_base_translation
+};
+use crate::auth::Claims;
+use crate::handlers::knowledge_base::{visible_to, ArticleResponse, TranslatedText};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TranslationRequest {
+    #[validate(length(min = 1, max = "MAX_TITLE_LEN"), custom = "validation::not_blank")]
+    pub title: String,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
+    pub content: String,
+    /// `draft` or `published`. New translations start as drafts.
+    #[validate(custom = "validation::translation_status")]
+    pub status: Option<String>,
+    /// Revision of the article that was translated. Defaults to the current one.
+    pub source_revision: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranslationResponse {
+    pub article_id: Uuid,
+    pub locale: String,
+    pub title: String,
+    pub content: String,
+    pub status: String,
+    pub source_revision: i32,
+    pub outdated: bool,
+    pub translated_by: Uuid,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+impl From<knowledge_base_translation::Model> for TranslationResponse {
+    fn from(translation: knowledge_base_translation::Model) -> Self {
+        TranslationResponse {
+            article_id: translation.article_id,
+            locale: translation.locale,
+            title: translation.title,
+            content: translation.content,
+            status: translation.status,
+            source_revision: translation.source_revision,
+            outdated: translation.outdated,
+            translated_by: translation.translated_by,
+            created_at: translation.created_at,
+            updated_at: translation.updated_at,
+        }
+    }
+}
+
+/// Every translation of the article, drafts included, by locale.
+pub async fn list_translations(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(article_id): Path<Uuid>,
+) -> Result<Json<Vec<TranslationResponse>>, AppError> {
+    claims.require_staff()?;
+    let article = find_article(&db, article_id, &claims).await?;
+
+    let translations = KnowledgeBaseTranslation::find()
+        .filter(knowledge_base_translation::Column::ArticleId.eq(article.id))
+        .order_by_asc(knowledge_base_translation::Column::Locale)
+        .all(&db)
+        .await?;
+
+    Ok(Json(translations.into_iter().map(TranslationResponse::from).collect()))
+}
+
+pub async fn get_translation(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((article_id, locale)): Path<(Uuid, String)>,
+) -> Result<Json<TranslationResponse>, AppError> {
+    claims.require_staff()?;
+    let article = find_article(&db, article_id, &claims).await?;
+
+    let translation = find_translation(&db, article.id, &locale)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(translation.into()))
+}
+
+/// Creates or replaces the translation into `locale`. The translation is outdated when
+/// the article's title or content has changed since `source_revision`. Staff may work
+/// on drafts; publishing a translation or changing a published one needs a KB editor.
+pub async fn put_translation(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((article_id, locale)): Path<(Uuid, String)>,
+    ValidatedJson(payload): ValidatedJson<TranslationRequest>,
+) -> Result<Json<TranslationResponse>, AppError> {
+    claims.require_staff()?;
+    if !LOCALES.contains(&locale.as_str()) {
+        return Err(AppError::bad_request(format!(
+            "`{locale}` is not supported; expected one of: {}",
+            LOCALES.join(", ")
+        )));
+    }
+
+    let txn = db.begin().await?;
+
+    // Locked so the outdated check sees the article's latest revision.
+    let article = KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(Some(&claims)))
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if locale == article.locale {
+        return Err(AppError::bad_request(format!(
+            "the article is written in `{locale}`; edit the article itself instead"
+        )));
+    }
+
+    let source_revision = payload.source_revision.unwrap_or(article.revision);
+    let source = KnowledgeBaseRevision::find()
+        .filter(knowledge_base_revision::Column::ArticleId.eq(article.id))
+        .filter(knowledge_base_revision::Column::Revision.eq(source_revision))
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::bad_request(format!("the article has no revision {source_revision}")))?;
+    let outdated = source.title != article.title || source.content != article.content;
+
+    let existing = KnowledgeBaseTranslation::find()
+        .filter(knowledge_base_translation::Column::ArticleId.eq(article.id))
+        .filter(knowledge_base_translation::Column::Locale.eq(&locale))
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+
+    let was_published = existing.as_ref().is_some_and(|translation| translation.status == "published");
+    let status = payload
+        .status
+        .or_else(|| existing.as_ref().map(|translation| translation.status.clone()))
+        .unwrap_or_else(|| "draft".to_string());
+    if (was_published || status == "published") && !claims.is_kb_editor() {
+        return Err(AppError::Forbidden);
+    }
+
+    let now = Utc::now();
+    let translation = match existing {
+        Some(translation) => {
+            let mut translation: knowledge_base_translation::ActiveModel = translation.into();
+            translation.title = Set(payload.title);
+            translation.content = Set(payload.content);
+            translation.status = Set(status);
+            translation.source_revision = Set(source_revision);
+            translation.outdated = Set(outdated);
+            translation.translated_by = Set(claims.user_id());
+            translation.updated_at = Set(now);
+            translation.update(&txn).await?
+        }
+        None => {
+            let translation = knowledge_base_translation::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                article_id: Set(article.id),
+                locale: Set(locale),
+                title: Set(payload.title),
+                content: Set(payload.content),
+                status: Set(status),
+                source_revision: Set(source_revision),
+                outdated: Set(outdated),
+                translated_by: Set(claims.user_id()),
+                created_at: Set(now),
+                updated_at: Set(now),
+            };
+            translation.insert(&txn).await?
+        }
+    };
+
+    txn.commit().await?;
+
+    Ok(Json(translation.into()))
+}
+
+/// Published translations can only be deleted by KB editors.
+pub async fn delete_translation(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((article_id, locale)): Path<(Uuid, String)>,
+) -> Result<StatusCode, AppError> {
+    claims.require_staff()?;
+    let article = find_article(&db, article_id, &claims).await?;
+
+    let translation = find_translation(&db, article.id, &locale)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if translation.status == "published" && !claims.is_kb_editor() {
+        return Err(AppError::Forbidden);
+    }
+
+    translation.delete(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The article in the caller's preferred language out of its own and those of its
+/// published translations, falling back to its own.
+pub(crate) async fn localize(
+    db: &DatabaseConnection,
+    article: knowledge_base::Model,
+    headers: &HeaderMap,
+) -> Result<ArticleResponse, DbErr> {
+    let translations = KnowledgeBaseTranslation::find()
+        .filter(knowledge_base_translation::Column::ArticleId.eq(article.id))
+        .filter(knowledge_base_translation::Column::Status.eq("published"))
+        .all(db)
+        .await?;
+
+    let available: Vec<&str> = std::iter::once(article.locale.as_str())
+        .chain(translations.iter().map(|translation| translation.locale.as_str()))
+        .collect();
+    let chosen = locale::negotiate(headers, &available).map(str::to_string);
+    let translation = translations
+        .into_iter()
+        .find(|translation| Some(&translation.locale) == chosen.as_ref());
+
+    let response = ArticleResponse::from(article);
+    Ok(match translation {
+        Some(translation) => response.translated(TranslatedText {
+            locale: translation.locale,
+            title: translation.title,
+            content: translation.content,
+            outdated: translation.outdated,
+        }),
+        None => response,
+    })
+}
+
+/// Flags the article's translations after its title or content changed.
+pub(crate) async fn mark_outdated<C: ConnectionTrait>(db: &C, article_id: Uuid) -> Result<(), DbErr> {
+    KnowledgeBaseTranslation::update_many()
+        .col_expr(knowledge_base_translation::Column::Outdated, Expr::value(true))
+        .filter(knowledge_base_translation::Column::ArticleId.eq(article_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// An article cannot be moved to a language it is already translated into.
+pub(crate) async fn check_locale_free<C: ConnectionTrait>(
+    db: &C,
+    article_id: Uuid,
+    locale: &str,
+) -> Result<(), AppError> {
+    if find_translation(db, article_id, locale).await?.is_some() {
+        Err(AppError::conflict(format!(
+            "the article has a `{locale}` translation; delete it before changing the article's locale"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+async fn find_article(db: &DatabaseConnection, article_id: Uuid, claims: &Claims) -> Result<knowledge_base::Model, AppError> {
+    KnowledgeBase::find_by_id(article_id)
+        .filter(visible_to(Some(claims)))
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+async fn find_translation<C: ConnectionTrait>(
+    db: &C,
+    article_id: Uuid,
+    locale: &str,
+) -> Result<Option<knowledge_base_translation::Model>, DbErr> {
+    KnowledgeBaseTranslation::find()
+        .filter(knowledge_base_translation::Column::ArticleId.eq(article_id))
+        .filter(knowledge_base_translation::Column::Locale.eq(locale))
+        .one(db)
+        .await
+}
\ No newline at end of file
@@ -0,0 +1,556 @@
+use axum::{
+    extract::{State, Extension},
+    http::StatusCode,
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    Condition, ModelTrait, TransactionTrait, FromQueryResult, sea_query::Expr
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+use chrono::Utc;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::{self, MAX_BODY_LEN, MAX_NAME_LEN, MAX_TAGS};
+use crate::models::{Macro, MacroApplication, Ticket, User, comment, macro_application, ticket, ticket_macro, user};
+use crate::auth::{Claims, STAFF_ROLES};
+use crate::handlers::comments::{self, CommentResponse};
+use crate::handlers::csat;
+use crate::handlers::tickets::TicketResponse;
+use crate::ws::{WsMessage, WsState};
+
+/// Placeholders a macro body may use, written as `{{customer.name}}`.
+const PLACEHOLDERS: &[&str] = &[
+    "ticket.id", "ticket.subject", "ticket.status", "ticket.priority",
+    "customer.name", "customer.email", "agent.name", "agent.email",
+];
+
+/// Ticket changes made when a macro is applied. Unset fields are left alone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct MacroActions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::status")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(custom = "validation::priority")]
+    pub priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assign_to: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(custom = "validation::tags")]
+    pub add_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(custom = "validation::tags")]
+    pub remove_tags: Vec<String>,
+}
+
+impl MacroActions {
+    fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.priority.is_none()
+            && self.assign_to.is_none()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "macro_does_something", skip_on_field_errors = false))]
+pub struct CreateMacroRequest {
+    #[validate(length(min = 1, max = "MAX_NAME_LEN"), custom = "validation::not_blank")]
+    pub name: String,
+    /// Markdown comment template; the macro only changes fields when omitted.
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "template")]
+    pub body: Option<String>,
+    /// Post the body as an internal note instead of a public reply.
+    #[serde(default)]
+    pub is_internal: bool,
+    #[serde(default)]
+    #[validate]
+    pub actions: MacroActions,
+    #[serde(default)]
+    pub is_shared: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateMacroRequest {
+    #[validate(length(min = 1, max = "MAX_NAME_LEN"), custom = "validation::not_blank")]
+    pub name: Option<String>,
+    /// `null` removes the body, as long as the macro keeps at least one action.
+    #[serde(default, deserialize_with = "validation::nullable")]
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "template")]
+    pub body: Option<Option<String>>,
+    pub is_internal: Option<bool>,
+    /// Replaces all actions.
+    #[validate]
+    pub actions: Option<MacroActions>,
+    pub is_shared: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MacroUsageQuery {
+    pub since: Option<chrono::DateTime<Utc>>,
+    pub until: Option<chrono::DateTime<Utc>>,
+    pub applied_by: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MacroResponse {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub body: Option<String>,
+    pub is_internal: bool,
+    pub actions: Value,
+    pub is_shared: bool,
+    /// Times the macro has been applied, by anyone.
+    pub usage_count: i64,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplyMacroResponse {
+    pub ticket: TicketResponse,
+    /// The comment posted from the macro's body, if it has one.
+    pub comment: Option<CommentResponse>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct MacroUsageResponse {
+    pub macro_id: Uuid,
+    pub name: String,
+    pub owner_id: Uuid,
+    pub is_shared: bool,
+    pub uses: i64,
+    /// Distinct agents who applied the macro in the period.
+    pub agents: i64,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct UsageRow {
+    macro_id: Uuid,
+    uses: i64,
+    last_used_at: Option<chrono::DateTime<Utc>>,
+}
+
+pub async fn create_macro(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreateMacroRequest>,
+) -> Result<Json<MacroResponse>, AppError> {
+    claims.require_staff()?;
+
+    let now = Utc::now();
+    let ticket_macro = ticket_macro::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(claims.user_id()),
+        name: Set(payload.name),
+        body: Set(payload.body),
+        is_internal: Set(payload.is_internal),
+        actions: Set(serde_json::to_value(payload.actions)?),
+        is_shared: Set(payload.is_shared),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let ticket_macro = ticket_macro.insert(&db).await?;
+
+    Ok(Json(macro_response(ticket_macro, &HashMap::new())))
+}
+
+/// The caller's own macros plus every shared one.
+pub async fn list_macros(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<MacroResponse>>, AppError> {
+    claims.require_staff()?;
+
+    let macros = Macro::find()
+        .filter(
+            Condition::any()
+                .add(ticket_macro::Column::OwnerId.eq(claims.user_id()))
+                .add(ticket_macro::Column::IsShared.eq(true)),
+        )
+        .order_by_asc(ticket_macro::Column::Name)
+        .all(&db)
+        .await?;
+
+    let usage = usage_by_macro(&db, macros.iter().map(|ticket_macro| ticket_macro.id)).await?;
+
+    Ok(Json(macros.into_iter().map(|ticket_macro| macro_response(ticket_macro, &usage)).collect()))
+}
+
+pub async fn get_macro(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(macro_id): Path<Uuid>,
+) -> Result<Json<MacroResponse>, AppError> {
+    let ticket_macro = find_visible_macro(&db, macro_id, &claims).await?;
+    let usage = usage_by_macro(&db, [ticket_macro.id]).await?;
+
+    Ok(Json(macro_response(ticket_macro, &usage)))
+}
+
+pub async fn update_macro(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(macro_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateMacroRequest>,
+) -> Result<Json<MacroResponse>, AppError> {
+    let ticket_macro = find_owned_macro(&db, macro_id, &claims).await?;
+
+    // The macro as it will be saved must still do something.
+    let body = payload.body.unwrap_or_else(|| ticket_macro.body.clone());
+    let actions = match payload.actions {
+        Some(actions) => actions,
+        None => serde_json::from_value(ticket_macro.actions.clone())?,
+    };
+    if let Err(error) = does_something(body.as_deref(), &actions) {
+        let mut errors = ValidationErrors::new();
+        errors.add("__all__", error);
+        return Err(errors.into());
+    }
+
+    let mut ticket_macro: ticket_macro::ActiveModel = ticket_macro.into();
+
+    if let Some(name) = payload.name {
+        ticket_macro.name = Set(name);
+    }
+    ticket_macro.body = Set(body);
+    if let Some(is_internal) = payload.is_internal {
+        ticket_macro.is_internal = Set(is_internal);
+    }
+    ticket_macro.actions = Set(serde_json::to_value(actions)?);
+    if let Some(is_shared) = payload.is_shared {
+        ticket_macro.is_shared = Set(is_shared);
+    }
+
+    ticket_macro.updated_at = Set(Utc::now());
+
+    let ticket_macro = ticket_macro.update(&db).await?;
+    let usage = usage_by_macro(&db, [ticket_macro.id]).await?;
+
+    Ok(Json(macro_response(ticket_macro, &usage)))
+}
+
+pub async fn delete_macro(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(macro_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let ticket_macro = find_owned_macro(&db, macro_id, &claims).await?;
+
+    ticket_macro.delete(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Applies the macro's field changes, posts its rendered body and records the use,
+/// all in one transaction. Placeholders see the ticket as it is after the changes.
+pub async fn apply_macro(
+    State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, macro_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApplyMacroResponse>, AppError> {
+    let ticket_macro = find_visible_macro(&db, macro_id, &claims).await?;
+    let actions: MacroActions = serde_json::from_value(ticket_macro.actions.clone())?;
+
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let customer = User::find_by_id(ticket.created_by).one(&db).await?;
+    let agent = User::find_by_id(claims.user_id())
+        .one(&db)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    if let Some(assignee) = actions.assign_to {
+        let is_staff = User::find_by_id(assignee)
+            .filter(user::Column::Role.is_in(STAFF_ROLES.iter().copied()))
+            .one(&db)
+            .await?
+            .is_some();
+        if !is_staff {
+            return Err(AppError::bad_request("the macro assigns tickets to a user who is not an agent"));
+        }
+    }
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+
+    let tags = merge_tags(&ticket.tags, &actions.add_tags, &actions.remove_tags)?;
+    let was_resolved = ticket.status == "Resolved";
+    let mut ticket: ticket::ActiveModel = ticket.into();
+    if let Some(status) = actions.status {
+        ticket.status = Set(status);
+    }
+    if let Some(priority) = actions.priority {
+        ticket.priority = Set(priority);
+    }
+    if let Some(assignee) = actions.assign_to {
+        ticket.assigned_to = Set(Some(assignee));
+    }
+    ticket.tags = Set(tags);
+    ticket.updated_at = Set(now);
+    let ticket = ticket.update(&txn).await?;
+
+    let comment = match &ticket_macro.body {
+        Some(body) => {
+            let content = render(body, |name| placeholder_value(name, &ticket, customer.as_ref(), &agent))
+                .map_err(AppError::bad_request)?;
+            let comment = comment::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                ticket_id: Set(ticket.id),
+                user_id: Set(agent.id),
+                content: Set(content),
+                is_internal: Set(ticket_macro.is_internal),
+                created_at: Set(now),
+                edited_at: Set(None),
+                deleted_at: Set(None),
+                deleted_by: Set(None),
+                parent_comment_id: Set(None),
+                email_message_id: Set(None),
+            };
+            Some(comment.insert(&txn).await?)
+        }
+        None => None,
+    };
+
+    let application = macro_application::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        macro_id: Set(ticket_macro.id),
+        ticket_id: Set(ticket.id),
+        applied_by: Set(agent.id),
+        applied_at: Set(now),
+    };
+    application.insert(&txn).await?;
+
+    txn.commit().await?;
+
+    if let Some(comment) = &comment {
+        comments::notify_comment_created(&db, &ws, &ticket, comment, &claims).await;
+    }
+    if !was_resolved && ticket.status == "Resolved" {
+        csat::send_survey(&db, &ticket).await;
+    }
+
+    let ticket = TicketResponse::from(ticket);
+    ws.publish(WsMessage::TicketUpdated { ticket: ticket.clone() });
+
+    Ok(Json(ApplyMacroResponse {
+        ticket,
+        comment: comment.map(CommentResponse::from),
+    }))
+}
+
+/// Uses per macro within an optional period, most used first.
+pub async fn get_macro_usage(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<MacroUsageQuery>,
+) -> Result<Json<Vec<MacroUsageResponse>>, AppError> {
+    claims.require_staff()?;
+
+    let mut select = MacroApplication::find()
+        .select_only()
+        .column(macro_application::Column::MacroId)
+        .column_as(Expr::cust("macros.name"), "name")
+        .column_as(Expr::cust("macros.owner_id"), "owner_id")
+        .column_as(Expr::cust("macros.is_shared"), "is_shared")
+        .column_as(Expr::cust("count(*)"), "uses")
+        .column_as(Expr::cust("count(DISTINCT macro_applications.applied_by)"), "agents")
+        .column_as(macro_application::Column::AppliedAt.max(), "last_used_at")
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            macro_application::Entity::belongs_to(Macro)
+                .from(macro_application::Column::MacroId)
+                .to(ticket_macro::Column::Id)
+                .into(),
+        )
+        .filter(
+            Condition::any()
+                .add(ticket_macro::Column::OwnerId.eq(claims.user_id()))
+                .add(ticket_macro::Column::IsShared.eq(true)),
+        )
+        .group_by(macro_application::Column::MacroId)
+        .group_by(ticket_macro::Column::Name)
+        .group_by(ticket_macro::Column::OwnerId)
+        .group_by(ticket_macro::Column::IsShared)
+        .order_by_desc(Expr::cust("count(*)"))
+        .order_by_asc(ticket_macro::Column::Name);
+
+    if let Some(since) = query.since {
+        select = select.filter(macro_application::Column::AppliedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(macro_application::Column::AppliedAt.lt(until));
+    }
+    if let Some(applied_by) = query.applied_by {
+        select = select.filter(macro_application::Column::AppliedBy.eq(applied_by));
+    }
+
+    let usage = select
+        .into_model::<MacroUsageResponse>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(usage))
+}
+
+async fn find_visible_macro(
+    db: &DatabaseConnection,
+    macro_id: Uuid,
+    claims: &Claims,
+) -> Result<ticket_macro::Model, AppError> {
+    claims.require_staff()?;
+
+    let ticket_macro = Macro::find_by_id(macro_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if ticket_macro.owner_id != claims.user_id() && !ticket_macro.is_shared {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(ticket_macro)
+}
+
+async fn find_owned_macro(
+    db: &DatabaseConnection,
+    macro_id: Uuid,
+    claims: &Claims,
+) -> Result<ticket_macro::Model, AppError> {
+    let ticket_macro = find_visible_macro(db, macro_id, claims).await?;
+
+    if ticket_macro.owner_id != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(ticket_macro)
+}
+
+async fn usage_by_macro(
+    db: &DatabaseConnection,
+    macro_ids: impl IntoIterator<Item = Uuid>,
+) -> Result<HashMap<Uuid, UsageRow>, AppError> {
+    let rows = MacroApplication::find()
+        .select_only()
+        .column(macro_application::Column::MacroId)
+        .column_as(Expr::cust("count(*)"), "uses")
+        .column_as(macro_application::Column::AppliedAt.max(), "last_used_at")
+        .filter(macro_application::Column::MacroId.is_in(macro_ids))
+        .group_by(macro_application::Column::MacroId)
+        .into_model::<UsageRow>()
+        .all(db)
+        .await?;
+
+    Ok(rows.into_iter().map(|row| (row.macro_id, row)).collect())
+}
+
+fn macro_response(ticket_macro: ticket_macro::Model, usage: &HashMap<Uuid, UsageRow>) -> MacroResponse {
+    let usage = usage.get(&ticket_macro.id);
+
+    MacroResponse {
+        id: ticket_macro.id,
+        owner_id: ticket_macro.owner_id,
+        name: ticket_macro.name,
+        body: ticket_macro.body,
+        is_internal: ticket_macro.is_internal,
+        actions: ticket_macro.actions,
+        is_shared: ticket_macro.is_shared,
+        usage_count: usage.map_or(0, |usage| usage.uses),
+        last_used_at: usage.and_then(|usage| usage.last_used_at),
+        created_at: ticket_macro.created_at,
+        updated_at: ticket_macro.updated_at,
+    }
+}
+
+/// Adds and removes tags, keeping the existing order and dropping duplicates.
+fn merge_tags(current: &Value, add: &[String], remove: &[String]) -> Result<Value, AppError> {
+    let mut tags: Vec<String> = serde_json::from_value(current.clone())?;
+    tags.retain(|tag| !remove.contains(tag));
+    for tag in add {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    if tags.len() > MAX_TAGS {
+        return Err(AppError::bad_request(format!("the ticket would have more than {MAX_TAGS} tags")));
+    }
+
+    Ok(serde_json::to_value(tags)?)
+}
+
+fn placeholder_value(
+    name: &str,
+    ticket: &ticket::Model,
+    customer: Option<&user::Model>,
+    agent: &user::Model,
+) -> Option<String> {
+    let value = match name {
+        "ticket.id" => ticket.id.to_string(),
+        "ticket.subject" => ticket.subject.clone(),
+        "ticket.status" => ticket.status.clone(),
+        "ticket.priority" => ticket.priority.clone(),
+        "customer.name" => customer.map(|customer| customer.name.clone()).unwrap_or_default(),
+        "customer.email" => customer.map(|customer| customer.email.clone()).unwrap_or_default(),
+        "agent.name" => agent.name.clone(),
+        "agent.email" => agent.email.clone(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Replaces each `{{ name }}` in `template` with `value(name)`, failing on an unknown
+/// placeholder or an unclosed `{{`.
+fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or("unclosed `{{` in template")?;
+        let name = after[..end].trim();
+        rendered.push_str(&value(name).ok_or_else(|| format!("unknown placeholder `{name}`"))?);
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+fn template(body: &str) -> Result<(), ValidationError> {
+    render(body, |name| PLACEHOLDERS.contains(&name).then(String::new))
+        .map(|_| ())
+        .map_err(|message| {
+            let mut error = ValidationError::new("template");
+            error.message = Some(format!("{message}; available: {}", PLACEHOLDERS.join(", ")).into());
+            error
+        })
+}
+
+fn macro_does_something(request: &CreateMacroRequest) -> Result<(), ValidationError> {
+    does_something(request.body.as_deref(), &request.actions)
+}
+
+fn does_something(body: Option<&str>, actions: &MacroActions) -> Result<(), ValidationError> {
+    if body.is_none() && actions.is_empty() {
+        let mut error = ValidationError::new("empty_macro");
+        error.message = Some("a macro needs a body or at least one action".into());
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
\ No newline at end of file
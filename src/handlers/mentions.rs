@@ -0,0 +1,299 @@
+use axum::{
+    extract::{State, Extension},
+    http::StatusCode,
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, QuerySelect, FromQueryResult, RelationTrait,
+    PaginatorTrait, Set, ConnectionTrait, JoinType, DbErr,
+    sea_query::{Expr, Func, OnConflict}
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+use chrono::Utc;
+
+use crate::extract::{Json, Path, Query};
+use crate::error::AppError;
+use crate::models::{CommentMention, User, comment, comment_mention, ticket, user};
+use crate::auth::{Claims, STAFF_ROLES};
+use crate::email;
+use crate::markdown;
+use crate::pagination::{self, Keyset, Page, PageRequest};
+use crate::ws::{WsMessage, WsState};
+
+/// Characters shown from the note in the inbox.
+const EXCERPT_LEN: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct MentionQuery {
+    /// `true` for unread only, `false` for read only; both when omitted.
+    pub unread: Option<bool>,
+    pub cursor: Option<String>,
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMentionRequest {
+    pub is_read: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MentionResponse {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub ticket_subject: String,
+    pub comment_id: Uuid,
+    /// Plain-text start of the note.
+    pub excerpt: String,
+    pub mentioned_by: Uuid,
+    pub mentioned_by_name: String,
+    pub is_read: bool,
+    pub read_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct MentionRow {
+    id: Uuid,
+    ticket_id: Uuid,
+    comment_id: Uuid,
+    mentioned_by: Uuid,
+    read_at: Option<chrono::DateTime<Utc>>,
+    created_at: chrono::DateTime<Utc>,
+    ticket_subject: String,
+    content: String,
+    mentioned_by_name: String,
+    sort_key: String,
+}
+
+/// The caller's mention inbox, newest first. Mentions in deleted notes are left out.
+pub async fn list_my_mentions(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<MentionQuery>,
+) -> Result<Json<Page<MentionResponse>>, AppError> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.page, query.limit)?;
+
+    let mut select = CommentMention::find()
+        .join(JoinType::InnerJoin, comment_mention::Relation::Comment.def())
+        .join(JoinType::InnerJoin, comment_mention::Relation::Ticket.def())
+        .join(JoinType::InnerJoin, comment_mention::Relation::MentionedBy.def())
+        .filter(comment_mention::Column::MentionedUserId.eq(claims.user_id()))
+        .filter(comment::Column::DeletedAt.is_null());
+
+    match query.unread {
+        Some(true) => select = select.filter(comment_mention::Column::ReadAt.is_null()),
+        Some(false) => select = select.filter(comment_mention::Column::ReadAt.is_not_null()),
+        None => {}
+    }
+
+    let total = select.clone().count(&db).await?;
+
+    let keyset = Keyset {
+        sort: "created_at",
+        expr: "comment_mentions.created_at".to_string(),
+        values: vec![],
+        cast: "timestamptz",
+        id_column: "comment_mentions.id",
+        descending: true,
+    };
+
+    let mentions = keyset
+        .apply(select, page.cursor.as_ref())?
+        .column_as(ticket::Column::Subject, "ticket_subject")
+        .column_as(comment::Column::Content, "content")
+        .column_as(user::Column::Name, "mentioned_by_name")
+        .offset(page.offset)
+        .limit(page.limit + 1)
+        .into_model::<MentionRow>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(pagination::into_page(
+        mentions,
+        &page,
+        total,
+        |mention| keyset.cursor(&mention.sort_key, mention.id),
+        |mention| MentionResponse {
+            id: mention.id,
+            ticket_id: mention.ticket_id,
+            ticket_subject: mention.ticket_subject,
+            comment_id: mention.comment_id,
+            excerpt: excerpt(&mention.content),
+            mentioned_by: mention.mentioned_by,
+            mentioned_by_name: mention.mentioned_by_name,
+            is_read: mention.read_at.is_some(),
+            read_at: mention.read_at,
+            created_at: mention.created_at,
+        },
+    )))
+}
+
+pub async fn update_mention(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(mention_id): Path<Uuid>,
+    Json(payload): Json<UpdateMentionRequest>,
+) -> Result<StatusCode, AppError> {
+    let read_at = payload.is_read.then(Utc::now);
+
+    let result = CommentMention::update_many()
+        .col_expr(comment_mention::Column::ReadAt, Expr::value(read_at))
+        .filter(comment_mention::Column::Id.eq(mention_id))
+        .filter(comment_mention::Column::MentionedUserId.eq(claims.user_id()))
+        .exec(&db)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn mark_all_mentions_read(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    CommentMention::update_many()
+        .col_expr(comment_mention::Column::ReadAt, Expr::value(Utc::now()))
+        .filter(comment_mention::Column::MentionedUserId.eq(claims.user_id()))
+        .filter(comment_mention::Column::ReadAt.is_null())
+        .exec(&db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Records mentions in an internal note and notifies each newly mentioned user over
+/// the websocket and by email. Only staff can be mentioned, never the author, and a
+/// user already mentioned in this note (before an edit) is not notified again.
+pub(crate) async fn record_mentions<C: ConnectionTrait>(
+    db: &C,
+    ws: &WsState,
+    ticket: &ticket::Model,
+    note: &comment::Model,
+    author: &Claims,
+) -> Result<(), DbErr> {
+    if !note.is_internal {
+        return Ok(());
+    }
+
+    let handles = mention_handles(&note.content);
+    if handles.is_empty() {
+        return Ok(());
+    }
+
+    let mentioned = User::find()
+        .filter(user::Column::Role.is_in(STAFF_ROLES.iter().copied()))
+        .filter(user::Column::Id.ne(author.user_id()))
+        .filter(
+            Expr::expr(Func::lower(Expr::col(user::Column::Email))).is_in(handles.clone())
+                .or(Expr::expr(Expr::cust("lower(regexp_replace(users.name, '\\s', '', 'g'))")).is_in(handles)),
+        )
+        .all(db)
+        .await?;
+
+    let already_mentioned: HashSet<Uuid> = CommentMention::find()
+        .filter(comment_mention::Column::CommentId.eq(note.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|mention| mention.mentioned_user_id)
+        .collect();
+
+    let new_mentions: Vec<user::Model> = mentioned
+        .into_iter()
+        .filter(|user| !already_mentioned.contains(&user.id))
+        .collect();
+    if new_mentions.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let rows = new_mentions.iter().map(|user| comment_mention::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        comment_id: Set(note.id),
+        ticket_id: Set(note.ticket_id),
+        mentioned_user_id: Set(user.id),
+        mentioned_by: Set(author.user_id()),
+        read_at: Set(None),
+        created_at: Set(now),
+    });
+
+    CommentMention::insert_many(rows)
+        .on_conflict(
+            OnConflict::columns([comment_mention::Column::CommentId, comment_mention::Column::MentionedUserId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    for user in new_mentions {
+        ws.publish(WsMessage::Mention {
+            ticket_id: note.ticket_id,
+            requester: ticket.created_by,
+            comment_id: note.id,
+            mentioned_user_id: user.id,
+            mentioned_by: author.user_id(),
+        });
+        email::notify_mention(user.email, note.ticket_id, note.id, author.email.clone(), note.content.clone());
+    }
+
+    Ok(())
+}
+
+/// Lower-cased handles from `@handle` tokens: an email address (`@jane@example.com`) or
+/// a name with its spaces removed (`@JaneDoe`). An `@` inside a word, as in a bare email
+/// address, does not start a mention.
+fn mention_handles(content: &str) -> Vec<String> {
+    let is_handle_char = |c: char| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '+' | '@');
+
+    let mut handles = Vec::new();
+    let mut previous = None;
+
+    for (index, c) in content.char_indices() {
+        if c == '@' && !previous.is_some_and(is_handle_char) {
+            let rest = &content[index + 1..];
+            let end = rest.find(|c: char| !is_handle_char(c)).unwrap_or(rest.len());
+            let handle = rest[..end].trim_end_matches(['.', '_', '-', '@']);
+            if !handle.is_empty() {
+                handles.push(handle.to_lowercase());
+            }
+        }
+        previous = Some(c);
+    }
+
+    handles.sort();
+    handles.dedup();
+    handles
+}
+
+fn excerpt(content: &str) -> String {
+    let text = markdown::to_plain_text(content);
+    match text.char_indices().nth(EXCERPT_LEN) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mention_handles_finds_names_and_emails() {
+        assert_eq!(
+            mention_handles("Ping @JaneDoe and @Bob@Example.com. Also @janedoe, again."),
+            ["bob@example.com", "janedoe"],
+        );
+    }
+
+    #[test]
+    fn mention_handles_ignores_bare_addresses() {
+        assert!(mention_handles("Mail jane@example.com or reply to @ anyone").is_empty());
+        assert_eq!(mention_handles("(@ann_lee)"), ["ann_lee"]);
+    }
+}
\ No newline at end of file
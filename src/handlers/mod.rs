@@ -1,4 +1,16 @@
 pub mod auth;
 pub mod tickets;
 pub mod comments;
-pub mod knowledge_base; 
\ No newline at end of file
+pub mod inbound_email;
+pub mod csat;
+pub mod attachments;
+pub mod mentions;
+pub mod knowledge_base;
+pub mod knowledge_base_analytics;
+pub mod knowledge_base_files;
+pub mod knowledge_base_translations;
+pub mod help_center;
+pub mod ticket_articles;
+pub mod views;
+pub mod users;
+pub mod macros; 
\ No newline at end of file
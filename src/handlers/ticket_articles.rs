@@ -0,0 +1,288 @@
+use axum::{
+    extract::{State, Extension},
+    http::StatusCode,
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder, QuerySelect,
+    ModelTrait, TransactionTrait, FromQueryResult, JoinType, sea_query::Expr
+};
+use serde::{Deserialize, Serialize};
+use std::{env, sync::Arc};
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::{is_unique_violation, AppError};
+use crate::markdown;
+use crate::validation::MAX_BODY_LEN;
+use crate::models::{KnowledgeBase, Ticket, TicketArticle, comment, knowledge_base, ticket, ticket_article};
+use crate::auth::Claims;
+use crate::handlers::comments::{self, CommentResponse};
+use crate::handlers::knowledge_base::{visible_to, LIVE};
+use crate::ws::WsState;
+
+/// Article links in comments point here, followed by the slug, when `KB_ARTICLE_URL`
+/// is not set. Customers follow them, so they go to the public help center.
+const DEFAULT_ARTICLE_URL: &str = "http://localhost:3000/help-center/articles/by-slug";
+const ALREADY_LINKED: &str = "the article is already linked to this ticket";
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct LinkArticleRequest {
+    pub article_id: Uuid,
+    /// Also post a public comment linking to the article.
+    #[serde(default)]
+    pub post_comment: bool,
+    /// Text above the link in the comment.
+    #[validate(length(max = "MAX_BODY_LEN"))]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArticleUsageQuery {
+    pub since: Option<chrono::DateTime<Utc>>,
+    pub until: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct TicketArticleResponse {
+    pub article_id: Uuid,
+    pub title: String,
+    pub linked_by: Uuid,
+    /// The comment the article was sent in, if any.
+    pub comment_id: Option<Uuid>,
+    pub linked_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkArticleResponse {
+    #[serde(flatten)]
+    pub link: TicketArticleResponse,
+    pub comment: Option<CommentResponse>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ArticleUsageResponse {
+    pub article_id: Uuid,
+    pub title: String,
+    /// Tickets the article was linked to in the period.
+    pub tickets: i64,
+    /// Of those, tickets that are now resolved or closed.
+    pub resolved_tickets: i64,
+    pub last_linked_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// Records that an article was used on a ticket. With `post_comment`, the article is
+/// also sent to the customer, so it must be live and not internal.
+pub async fn link_article(
+    State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<LinkArticleRequest>,
+) -> Result<(StatusCode, Json<LinkArticleResponse>), AppError> {
+    claims.require_staff()?;
+
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let article = KnowledgeBase::find_by_id(payload.article_id)
+        .filter(visible_to(Some(&claims)))
+        .one(&db)
+        .await?
+        .ok_or_else(|| AppError::bad_request("the article does not exist"))?;
+
+    let existing = TicketArticle::find()
+        .filter(ticket_article::Column::TicketId.eq(ticket.id))
+        .filter(ticket_article::Column::ArticleId.eq(article.id))
+        .one(&db)
+        .await?;
+    if existing.is_some() {
+        return Err(AppError::conflict(ALREADY_LINKED));
+    }
+
+    if payload.post_comment {
+        let sendable = KnowledgeBase::find_by_id(article.id)
+            .filter(Expr::cust(LIVE))
+            .filter(knowledge_base::Column::Visibility.ne("internal"))
+            .one(&db)
+            .await?
+            .is_some();
+        if !sendable {
+            return Err(AppError::bad_request("only live articles customers can read may be sent in a comment"));
+        }
+    }
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+
+    let comment = if payload.post_comment {
+        let base_url = env::var("KB_ARTICLE_URL").unwrap_or_else(|_| DEFAULT_ARTICLE_URL.to_string());
+        let link = format!("[{}]({base_url}/{})", markdown::escape(&article.title), article.slug);
+        let content = match payload.message.as_deref().map(str::trim).filter(|message| !message.is_empty()) {
+            Some(message) => format!("{message}\n\n{link}"),
+            None => link,
+        };
+        let comment = comment::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            ticket_id: Set(ticket.id),
+            user_id: Set(claims.user_id()),
+            content: Set(content),
+            is_internal: Set(false),
+            created_at: Set(now),
+            edited_at: Set(None),
+            deleted_at: Set(None),
+            deleted_by: Set(None),
+            parent_comment_id: Set(None),
+            email_message_id: Set(None),
+        };
+        Some(comment.insert(&txn).await?)
+    } else {
+        None
+    };
+
+    let link = ticket_article::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        ticket_id: Set(ticket.id),
+        article_id: Set(article.id),
+        linked_by: Set(claims.user_id()),
+        comment_id: Set(comment.as_ref().map(|comment| comment.id)),
+        linked_at: Set(now),
+    };
+    // The check above cannot stop a concurrent request linking the same article.
+    let link = match link.insert(&txn).await {
+        Ok(link) => link,
+        Err(e) if is_unique_violation(&e) => return Err(AppError::conflict(ALREADY_LINKED)),
+        Err(e) => return Err(e.into()),
+    };
+
+    txn.commit().await?;
+
+    if let Some(comment) = &comment {
+        comments::notify_comment_created(&db, &ws, &ticket, comment, &claims).await;
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(LinkArticleResponse {
+            link: TicketArticleResponse {
+                article_id: link.article_id,
+                title: article.title,
+                linked_by: link.linked_by,
+                comment_id: link.comment_id,
+                linked_at: link.linked_at,
+            },
+            comment: comment.map(CommentResponse::from),
+        }),
+    ))
+}
+
+/// Articles used on the ticket, oldest first. Customers only see articles they can read.
+pub async fn list_ticket_articles(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<Vec<TicketArticleResponse>>, AppError> {
+    let ticket = Ticket::find_by_id(ticket_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if !claims.is_staff() && ticket.created_by != claims.user_id() {
+        return Err(AppError::Forbidden);
+    }
+
+    let links = TicketArticle::find()
+        .select_only()
+        .columns([
+            ticket_article::Column::ArticleId,
+            ticket_article::Column::LinkedBy,
+            ticket_article::Column::CommentId,
+            ticket_article::Column::LinkedAt,
+        ])
+        .column(knowledge_base::Column::Title)
+        .join(JoinType::InnerJoin, article_relation())
+        .filter(ticket_article::Column::TicketId.eq(ticket.id))
+        .filter(visible_to(Some(&claims)))
+        .order_by_asc(ticket_article::Column::LinkedAt)
+        .into_model::<TicketArticleResponse>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(links))
+}
+
+/// Removes the link. A comment already sent with the article is kept.
+pub async fn unlink_article(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path((ticket_id, article_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    claims.require_staff()?;
+
+    let link = TicketArticle::find()
+        .filter(ticket_article::Column::TicketId.eq(ticket_id))
+        .filter(ticket_article::Column::ArticleId.eq(article_id))
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    link.delete(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Tickets each article was linked to in the period and how many of them are now
+/// resolved or closed, most resolutions first.
+pub async fn get_article_usage(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ArticleUsageQuery>,
+) -> Result<Json<Vec<ArticleUsageResponse>>, AppError> {
+    claims.require_staff()?;
+
+    let resolved = "count(*) FILTER (WHERE tickets.status IN ('Resolved', 'Closed'))";
+    let mut select = TicketArticle::find()
+        .select_only()
+        .column(ticket_article::Column::ArticleId)
+        .column(knowledge_base::Column::Title)
+        .column_as(Expr::cust("count(*)"), "tickets")
+        .column_as(Expr::cust(resolved), "resolved_tickets")
+        .column_as(ticket_article::Column::LinkedAt.max(), "last_linked_at")
+        .join(JoinType::InnerJoin, article_relation())
+        .join(
+            JoinType::InnerJoin,
+            ticket_article::Entity::belongs_to(Ticket)
+                .from(ticket_article::Column::TicketId)
+                .to(ticket::Column::Id)
+                .into(),
+        )
+        .filter(visible_to(Some(&claims)))
+        .group_by(ticket_article::Column::ArticleId)
+        .group_by(knowledge_base::Column::Title)
+        .order_by_desc(Expr::cust(resolved))
+        .order_by_desc(Expr::cust("count(*)"))
+        .order_by_asc(knowledge_base::Column::Title);
+
+    if let Some(since) = query.since {
+        select = select.filter(ticket_article::Column::LinkedAt.gte(since));
+    }
+    if let Some(until) = query.until {
+        select = select.filter(ticket_article::Column::LinkedAt.lt(until));
+    }
+
+    let usage = select
+        .into_model::<ArticleUsageResponse>()
+        .all(&db)
+        .await?;
+
+    Ok(Json(usage))
+}
+
+fn article_relation() -> sea_orm::RelationDef {
+    ticket_article::Entity::belongs_to(KnowledgeBase)
+        .from(ticket_article::Column::ArticleId)
+        .to(knowledge_base::Column::Id)
+        .into()
+}
\ No newline at end of file
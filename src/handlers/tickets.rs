@@ -1,46 +1,194 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{State, Extension},
     http::StatusCode,
-    Json,
 };
 use sea_orm::{
-    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder,
-    Condition, ModelTrait, QuerySelect
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set,
+    Condition, ModelTrait, QuerySelect, FromQueryResult, Select, PaginatorTrait, sea_query::Expr
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 use chrono::Utc;
+use std::{fmt, str::FromStr, sync::Arc};
+use validator::Validate;
 
-use crate::models::{Ticket, ticket};
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::models::{Attachment, Ticket, attachment, ticket};
+use crate::validation::{self, MAX_BODY_LEN, MAX_SUBJECT_LEN};
+use crate::pagination::{self, Keyset, Page, PageRequest, parse_sort};
 use crate::auth::Claims;
+use crate::storage::StorageBackend;
+use crate::handlers::{attachments, csat};
+use crate::ws::{WsMessage, WsState};
 
-#[derive(Debug, Deserialize)]
+// These must stay in sync with `idx_tickets_search` and `idx_comments_search`,
+// otherwise Postgres falls back to a sequential scan.
+const TICKET_DOCUMENT: &str = "to_tsvector('english', tickets.subject || ' ' || tickets.description)";
+const COMMENT_DOCUMENT: &str = "to_tsvector('english', comments.content)";
+const SEARCH_QUERY: &str = "websearch_to_tsquery('english', $1)";
+const HEADLINE_OPTIONS: &str = "'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=25, MinWords=8'";
+
+/// Comment matches count for less than a match in the ticket itself.
+const COMMENT_RANK_WEIGHT: f32 = 0.5;
+
+const PRIORITY_ORDER: &str = "CASE tickets.priority \
+    WHEN 'Critical' THEN 4 WHEN 'High' THEN 3 WHEN 'Medium' THEN 2 WHEN 'Low' THEN 1 ELSE 0 END";
+const STATUS_ORDER: &str = "CASE tickets.status \
+    WHEN 'Open' THEN 1 WHEN 'In Progress' THEN 2 WHEN 'Pending' THEN 3 \
+    WHEN 'Resolved' THEN 4 WHEN 'Closed' THEN 5 ELSE 6 END";
+/// Response deadlines per priority, as documented in the README.
+const SLA_DUE_AT: &str = "tickets.created_at + CASE tickets.priority \
+    WHEN 'Critical' THEN interval '1 hour' WHEN 'High' THEN interval '4 hours' \
+    WHEN 'Low' THEN interval '48 hours' ELSE interval '24 hours' END";
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateTicketRequest {
+    #[validate(length(min = 1, max = "MAX_SUBJECT_LEN"), custom = "validation::not_blank")]
     pub subject: String,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
     pub description: String,
+    #[validate(custom = "validation::priority")]
     pub priority: String,
+    #[serde(default)]
+    #[validate(custom = "validation::tags")]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateTicketRequest {
+    #[validate(length(min = 1, max = "MAX_SUBJECT_LEN"), custom = "validation::not_blank")]
     pub subject: Option<String>,
+    #[validate(length(min = 1, max = "MAX_BODY_LEN"), custom = "validation::not_blank")]
     pub description: Option<String>,
+    #[validate(custom = "validation::status")]
     pub status: Option<String>,
+    #[validate(custom = "validation::priority")]
     pub priority: Option<String>,
     pub assigned_to: Option<Uuid>,
+    #[validate(custom = "validation::tags")]
+    pub tags: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TicketQuery {
+/// Ticket filters accepted by `list_tickets` and stored as JSON by saved views.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TicketFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub assigned_to: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub created_by: Option<Uuid>,
+    /// Free-text query in `websearch_to_tsquery` syntax, e.g. `login -"password reset"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    /// Whether `search` may match internal notes. Set from the caller's role, never
+    /// from the request or a stored view.
+    #[serde(skip)]
+    pub include_internal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketSortKey {
+    CreatedAt,
+    UpdatedAt,
+    /// Critical > High > Medium > Low.
+    Priority,
+    /// Workflow order: Open, In Progress, Pending, Resolved, Closed.
+    Status,
+    /// First-response deadline from the priority's SLA.
+    SlaDueAt,
+    /// Search rank; only meaningful together with `search`.
+    Relevance,
+}
+
+/// A `sort=` value such as `-updated_at` or `sla_due_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TicketSort {
+    pub key: TicketSortKey,
+    pub descending: bool,
+}
+
+impl TicketSort {
+    const NEWEST_FIRST: TicketSort = TicketSort { key: TicketSortKey::CreatedAt, descending: true };
+    const MOST_RELEVANT: TicketSort = TicketSort { key: TicketSortKey::Relevance, descending: true };
+
+    /// The requested sort, defaulting to relevance when searching and newest first otherwise.
+    pub fn resolve(sort: Option<TicketSort>, filters: &TicketFilters) -> TicketSort {
+        match (sort, search_term(filters)) {
+            (Some(TicketSort { key: TicketSortKey::Relevance, .. }), None) => TicketSort::NEWEST_FIRST,
+            (Some(sort), _) => sort,
+            (None, Some(_)) => TicketSort::MOST_RELEVANT,
+            (None, None) => TicketSort::NEWEST_FIRST,
+        }
+    }
+}
+
+impl FromStr for TicketSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (field, descending) = parse_sort(value);
+        let key = match field {
+            "created_at" => TicketSortKey::CreatedAt,
+            "updated_at" => TicketSortKey::UpdatedAt,
+            "priority" => TicketSortKey::Priority,
+            "status" => TicketSortKey::Status,
+            "sla_due_at" => TicketSortKey::SlaDueAt,
+            "relevance" => TicketSortKey::Relevance,
+            _ => return Err(format!("unknown sort field `{field}`")),
+        };
+        Ok(TicketSort { key, descending })
+    }
+}
+
+impl TryFrom<String> for TicketSort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for TicketSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.key {
+            TicketSortKey::CreatedAt => "created_at",
+            TicketSortKey::UpdatedAt => "updated_at",
+            TicketSortKey::Priority => "priority",
+            TicketSortKey::Status => "status",
+            TicketSortKey::SlaDueAt => "sla_due_at",
+            TicketSortKey::Relevance => "relevance",
+        };
+        if self.descending {
+            write!(f, "-{field}")
+        } else {
+            f.write_str(field)
+        }
+    }
+}
+
+impl From<TicketSort> for String {
+    fn from(sort: TicketSort) -> Self {
+        sort.to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TicketQuery {
+    #[serde(flatten)]
+    pub filters: TicketFilters,
+    pub sort: Option<TicketSort>,
+    pub cursor: Option<String>,
     pub page: Option<u64>,
     pub limit: Option<u64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TicketResponse {
     pub id: Uuid,
     pub subject: String,
@@ -48,16 +196,63 @@ pub struct TicketResponse {
     pub status: String,
     pub priority: String,
     pub assigned_to: Option<Uuid>,
+    pub tags: Value,
     pub created_by: Uuid,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
+impl From<ticket::Model> for TicketResponse {
+    fn from(ticket: ticket::Model) -> Self {
+        TicketResponse {
+            id: ticket.id,
+            subject: ticket.subject,
+            description: ticket.description,
+            status: ticket.status,
+            priority: ticket.priority,
+            assigned_to: ticket.assigned_to,
+            tags: ticket.tags,
+            created_by: ticket.created_by,
+            created_at: ticket.created_at,
+            updated_at: ticket.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketListItem {
+    #[serde(flatten)]
+    pub ticket: TicketResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct TicketRow {
+    id: Uuid,
+    subject: String,
+    description: String,
+    status: String,
+    priority: String,
+    assigned_to: Option<Uuid>,
+    tags: Value,
+    created_by: Uuid,
+    created_at: chrono::DateTime<Utc>,
+    updated_at: chrono::DateTime<Utc>,
+    search_rank: Option<f32>,
+    snippet: Option<String>,
+    sort_key: String,
+}
+
 pub async fn create_ticket(
     State(db): State<DatabaseConnection>,
-    Json(payload): Json<CreateTicketRequest>,
-) -> Result<Json<TicketResponse>, StatusCode> {
-    let user_id = uuid::Uuid::new_v4();
+    State(ws): State<Arc<WsState>>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreateTicketRequest>,
+) -> Result<Json<TicketResponse>, AppError> {
+    let user_id = claims.user_id();
     let ticket_id = Uuid::new_v4();
     let now = Utc::now();
 
@@ -68,60 +263,42 @@ pub async fn create_ticket(
         status: Set("Open".to_string()),
         priority: Set(payload.priority),
         assigned_to: Set(None),
+        tags: Set(serde_json::to_value(payload.tags)?),
         created_by: Set(user_id),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
-    let ticket = ticket.insert(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ticket = TicketResponse::from(ticket.insert(&db).await?);
+    ws.publish(WsMessage::TicketCreated { ticket: ticket.clone() });
 
-    Ok(Json(TicketResponse {
-        id: ticket.id,
-        subject: ticket.subject,
-        description: ticket.description,
-        status: ticket.status,
-        priority: ticket.priority,
-        assigned_to: ticket.assigned_to,
-        created_by: ticket.created_by,
-        created_at: ticket.created_at,
-        updated_at: ticket.updated_at,
-    }))
+    Ok(Json(ticket))
 }
 
 pub async fn get_ticket(
     State(db): State<DatabaseConnection>,
     Path(ticket_id): Path<Uuid>,
-) -> Result<Json<TicketResponse>, StatusCode> {
+) -> Result<Json<TicketResponse>, AppError> {
     let ticket = Ticket::find_by_id(ticket_id)
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    Ok(Json(TicketResponse {
-        id: ticket.id,
-        subject: ticket.subject,
-        description: ticket.description,
-        status: ticket.status,
-        priority: ticket.priority,
-        assigned_to: ticket.assigned_to,
-        created_by: ticket.created_by,
-        created_at: ticket.created_at,
-        updated_at: ticket.updated_at,
-    }))
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(ticket.into()))
 }
 
 pub async fn update_ticket(
     State(db): State<DatabaseConnection>,
+    State(ws): State<Arc<WsState>>,
     Path(ticket_id): Path<Uuid>,
-    Json(payload): Json<UpdateTicketRequest>,
-) -> Result<Json<TicketResponse>, StatusCode> {
+    ValidatedJson(payload): ValidatedJson<UpdateTicketRequest>,
+) -> Result<Json<TicketResponse>, AppError> {
     let ticket = Ticket::find_by_id(ticket_id)
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or(AppError::NotFound)?;
 
+    let was_resolved = ticket.status == "Resolved";
     let mut ticket: ticket::ActiveModel = ticket.into();
     
     if let Some(subject) = payload.subject {
@@ -139,85 +316,215 @@ pub async fn update_ticket(
     if let Some(assigned_to) = payload.assigned_to {
         ticket.assigned_to = Set(Some(assigned_to));
     }
+    if let Some(tags) = payload.tags {
+        ticket.tags = Set(serde_json::to_value(tags)?);
+    }
     
     ticket.updated_at = Set(Utc::now());
 
-    let ticket = ticket.update(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ticket = ticket.update(&db).await?;
 
-    Ok(Json(TicketResponse {
-        id: ticket.id,
-        subject: ticket.subject,
-        description: ticket.description,
-        status: ticket.status,
-        priority: ticket.priority,
-        assigned_to: ticket.assigned_to,
-        created_by: ticket.created_by,
-        created_at: ticket.created_at,
-        updated_at: ticket.updated_at,
-    }))
+    if !was_resolved && ticket.status == "Resolved" {
+        csat::send_survey(&db, &ticket).await;
+    }
+
+    let ticket = TicketResponse::from(ticket);
+    ws.publish(WsMessage::TicketUpdated { ticket: ticket.clone() });
+
+    Ok(Json(ticket))
 }
 
+/// Attachment rows go with the ticket via `ON DELETE CASCADE`; their stored files are removed here.
 pub async fn delete_ticket(
     State(db): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn StorageBackend>>,
+    State(ws): State<Arc<WsState>>,
     Path(ticket_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, AppError> {
     let ticket = Ticket::find_by_id(ticket_id)
         .one(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let storage_keys: Vec<String> = Attachment::find()
+        .filter(attachment::Column::TicketId.eq(ticket.id))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|attachment| attachment.storage_key)
+        .collect();
 
-    ticket.delete(&db).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (ticket_id, created_by) = (ticket.id, ticket.created_by);
+    ticket.delete(&db).await?;
+    attachments::discard(storage.as_ref(), &storage_keys).await;
+    ws.publish(WsMessage::TicketDeleted { ticket_id, created_by });
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn list_tickets(
     State(db): State<DatabaseConnection>,
-    Query(query): Query<TicketQuery>,
-) -> Result<Json<Vec<TicketResponse>>, StatusCode> {
+    Extension(claims): Extension<Claims>,
+    Query(mut query): Query<TicketQuery>,
+) -> Result<Json<Page<TicketListItem>>, AppError> {
+    query.filters.include_internal = claims.is_staff();
+    let page = PageRequest::new(query.cursor.as_deref(), query.page, query.limit)?;
+    let sort = TicketSort::resolve(query.sort, &query.filters);
+
+    let tickets = fetch_tickets(&db, &query.filters, sort, &page)
+        .await?;
+
+    Ok(Json(tickets))
+}
+
+fn search_term(filters: &TicketFilters) -> Option<&str> {
+    filters
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+}
+
+/// Correlates `comments` with the outer ticket, limited to the comments `filters` may search.
+fn searchable_comments(filters: &TicketFilters) -> &'static str {
+    if filters.include_internal {
+        "comments.ticket_id = tickets.id AND comments.deleted_at IS NULL"
+    } else {
+        "comments.ticket_id = tickets.id AND comments.deleted_at IS NULL AND NOT comments.is_internal"
+    }
+}
+
+fn rank_expr(filters: &TicketFilters) -> String {
+    let comments = searchable_comments(filters);
+    format!(
+        "(ts_rank({TICKET_DOCUMENT}, {SEARCH_QUERY}) + COALESCE((\
+            SELECT max(ts_rank({COMMENT_DOCUMENT}, {SEARCH_QUERY})) FROM comments \
+            WHERE {comments}), 0) * {COMMENT_RANK_WEIGHT})::real"
+    )
+}
+
+fn keyset(sort: TicketSort, filters: &TicketFilters) -> Keyset {
+    let (name, expr, values, cast) = match (sort.key, search_term(filters)) {
+        (TicketSortKey::Relevance, Some(search)) => ("relevance", rank_expr(filters), vec![search.into()], "real"),
+        (TicketSortKey::UpdatedAt, _) => ("updated_at", "tickets.updated_at".to_string(), vec![], "timestamptz"),
+        (TicketSortKey::Priority, _) => ("priority", PRIORITY_ORDER.to_string(), vec![], "int"),
+        (TicketSortKey::Status, _) => ("status", STATUS_ORDER.to_string(), vec![], "int"),
+        (TicketSortKey::SlaDueAt, _) => ("sla_due_at", SLA_DUE_AT.to_string(), vec![], "timestamptz"),
+        (TicketSortKey::CreatedAt | TicketSortKey::Relevance, _) => {
+            ("created_at", "tickets.created_at".to_string(), vec![], "timestamptz")
+        }
+    };
+
+    Keyset {
+        sort: name,
+        expr,
+        values,
+        cast,
+        id_column: "tickets.id",
+        descending: sort.descending,
+    }
+}
+
+/// Tickets matching `filters`, without any ordering or extra columns.
+pub(crate) fn filtered_tickets(filters: &TicketFilters) -> Select<Ticket> {
     let mut condition = Condition::all();
 
-    if let Some(status) = query.status {
+    if let Some(status) = &filters.status {
         condition = condition.add(ticket::Column::Status.eq(status));
     }
-    if let Some(priority) = query.priority {
+    if let Some(priority) = &filters.priority {
         condition = condition.add(ticket::Column::Priority.eq(priority));
     }
-    if let Some(assigned_to) = query.assigned_to {
+    if let Some(assigned_to) = filters.assigned_to {
         condition = condition.add(ticket::Column::AssignedTo.eq(assigned_to));
     }
-    if let Some(created_by) = query.created_by {
+    if let Some(created_by) = filters.created_by {
         condition = condition.add(ticket::Column::CreatedBy.eq(created_by));
     }
+    if let Some(search) = search_term(filters) {
+        let comments = searchable_comments(filters);
+        condition = condition.add(Expr::cust_with_values(
+            format!(
+                "({TICKET_DOCUMENT} @@ {SEARCH_QUERY} OR EXISTS (\
+                    SELECT 1 FROM comments \
+                    WHERE {comments} AND {COMMENT_DOCUMENT} @@ {SEARCH_QUERY}))"
+            ),
+            [search],
+        ));
+    }
 
-    let page = query.page.unwrap_or(1);
-    let limit = query.limit.unwrap_or(50);
-    let offset = (page - 1) * limit;
+    Ticket::find().filter(condition)
+}
 
-    let tickets = Ticket::find()
-        .filter(condition)
-        .order_by_desc(ticket::Column::CreatedAt)
-        .offset(offset)
-        .limit(limit)
-        .all(&db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+/// One page of tickets matching `filters`, with search rank and snippet when searching.
+pub(crate) async fn fetch_tickets(
+    db: &DatabaseConnection,
+    filters: &TicketFilters,
+    sort: TicketSort,
+    page: &PageRequest,
+) -> Result<Page<TicketListItem>, AppError> {
+    let total = filtered_tickets(filters).count(db).await?;
 
-    let responses: Vec<TicketResponse> = tickets
-        .into_iter()
-        .map(|ticket| TicketResponse {
-            id: ticket.id,
-            subject: ticket.subject,
-            description: ticket.description,
-            status: ticket.status,
-            priority: ticket.priority,
-            assigned_to: ticket.assigned_to,
-            created_by: ticket.created_by,
-            created_at: ticket.created_at,
-            updated_at: ticket.updated_at,
-        })
-        .collect();
+    let mut select = filtered_tickets(filters);
+
+    match search_term(filters) {
+        Some(search) => {
+            let comments = searchable_comments(filters);
+            select = select
+                .column_as(Expr::cust_with_values(rank_expr(filters), [search]), "search_rank")
+                .column_as(
+                    Expr::cust_with_values(
+                        format!(
+                            "CASE WHEN {TICKET_DOCUMENT} @@ {SEARCH_QUERY} \
+                            THEN ts_headline('english', tickets.subject || ' ' || tickets.description, {SEARCH_QUERY}, {HEADLINE_OPTIONS}) \
+                            ELSE (SELECT ts_headline('english', comments.content, {SEARCH_QUERY}, {HEADLINE_OPTIONS}) \
+                                FROM comments \
+                                WHERE {comments} AND {COMMENT_DOCUMENT} @@ {SEARCH_QUERY} \
+                                ORDER BY ts_rank({COMMENT_DOCUMENT}, {SEARCH_QUERY}) DESC \
+                                LIMIT 1) \
+                            END"
+                        ),
+                        [search],
+                    ),
+                    "snippet",
+                );
+        }
+        None => {
+            select = select
+                .column_as(Expr::cust("NULL::real"), "search_rank")
+                .column_as(Expr::cust("NULL::text"), "snippet");
+        }
+    }
 
-    Ok(Json(responses))
-} 
\ No newline at end of file
+    let keyset = keyset(sort, filters);
+    let tickets = keyset
+        .apply(select, page.cursor.as_ref())?
+        .offset(page.offset)
+        .limit(page.limit + 1)
+        .into_model::<TicketRow>()
+        .all(db)
+        .await?;
+
+    Ok(pagination::into_page(
+        tickets,
+        page,
+        total,
+        |ticket| keyset.cursor(&ticket.sort_key, ticket.id),
+        |ticket| TicketListItem {
+            ticket: TicketResponse {
+                id: ticket.id,
+                subject: ticket.subject,
+                description: ticket.description,
+                status: ticket.status,
+                priority: ticket.priority,
+                assigned_to: ticket.assigned_to,
+                tags: ticket.tags,
+                created_by: ticket.created_by,
+                created_at: ticket.created_at,
+                updated_at: ticket.updated_at,
+            },
+            rank: ticket.search_rank,
+            snippet: ticket.snippet,
+        },
+    ))
+}
@@ -0,0 +1,48 @@
+use axum::extract::{State, Extension};
+use sea_orm::{DatabaseConnection, EntityTrait, ActiveModelTrait, Set};
+use serde::Deserialize;
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use crate::extract::{Json, Path, ValidatedJson};
+use crate::error::AppError;
+use crate::validation;
+use crate::models::{User, user};
+use crate::auth::Claims;
+use crate::handlers::auth::UserResponse;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRoleRequest {
+    #[validate(custom = "validation::role")]
+    pub role: String,
+}
+
+/// Admins grant and revoke staff roles; `POST /auth/register` only creates customers.
+/// The new role applies from the user's next login.
+pub async fn update_user_role(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateRoleRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    if !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+    // Keeps an admin from locking themselves, and possibly everyone, out of admin.
+    if user_id == claims.user_id() {
+        return Err(AppError::bad_request("you cannot change your own role"));
+    }
+
+    let user = User::find_by_id(user_id)
+        .one(&db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let mut user: user::ActiveModel = user.into();
+    user.role = Set(payload.role);
+    user.updated_at = Set(Utc::now());
+    let user = user.update(&db).await?;
+
+    Ok(Json(user.into()))
+}
\ No newline at end of file
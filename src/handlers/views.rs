@@ -0,0 +1,383 @@
+use axum::{
+    extract::{State, Extension},
+    http::StatusCode,
+};
+use sea_orm::{
+    DatabaseConnection, EntityTrait, QueryFilter, ColumnTrait, ActiveModelTrait, Set, QueryOrder,
+    Condition, ModelTrait, QuerySelect, QueryTrait, ConnectionTrait, FromQueryResult,
+    sea_query::{Expr, OnConflict, UnionType}
+};
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use chrono::Utc;
+use validator::{Validate, ValidationError};
+
+use crate::extract::{Json, Path, Query, ValidatedJson};
+use crate::error::AppError;
+use crate::validation::{self, MAX_NAME_LEN};
+use crate::models::{SavedView, SavedViewVisit, saved_view, saved_view_visit};
+use crate::auth::Claims;
+use crate::pagination::{Page, PageRequest};
+use crate::handlers::tickets::{self, TicketFilters, TicketListItem, TicketSort};
+
+/// Ticket fields a view may choose to display.
+const VIEW_COLUMNS: &[&str] = &[
+    "id", "subject", "description", "status", "priority", "assigned_to", "tags", "created_by", "created_at",
+    "updated_at",
+];
+
+/// Filters stored with a view. `assigned_to_me` is resolved against whoever opens the
+/// view, so a shared "My open tickets" view works for every agent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ViewFilters {
+    #[serde(flatten)]
+    pub tickets: TicketFilters,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub assigned_to_me: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateViewRequest {
+    #[validate(length(min = 1, max = "MAX_NAME_LEN"), custom = "validation::not_blank")]
+    pub name: String,
+    #[serde(default)]
+    pub filters: ViewFilters,
+    pub sort: Option<TicketSort>,
+    #[serde(default)]
+    #[validate(custom = "view_columns")]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub is_shared: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateViewRequest {
+    #[validate(length(min = 1, max = "MAX_NAME_LEN"), custom = "validation::not_blank")]
+    pub name: Option<String>,
+    pub filters: Option<ViewFilters>,
+    /// `null` goes back to the default order.
+    #[serde(default, deserialize_with = "validation::nullable")]
+    pub sort: Option<Option<TicketSort>>,
+    #[validate(custom = "view_columns")]
+    pub columns: Option<Vec<String>>,
+    pub is_shared: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ViewTicketsQuery {
+    pub cursor: Option<String>,
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ViewResponse {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub filters: Value,
+    pub sort: Option<String>,
+    pub columns: Value,
+    pub is_shared: bool,
+    /// Tickets currently matching the view.
+    pub total: u64,
+    /// Matching tickets created or updated since the caller last opened the view.
+    pub new: u64,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ViewCounts {
+    view_id: Uuid,
+    total: i64,
+    new: i64,
+}
+
+/// Only staff may share a view, as shared views are listed for every agent.
+pub async fn create_view(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreateViewRequest>,
+) -> Result<Json<ViewResponse>, AppError> {
+    if payload.is_shared {
+        claims.require_staff()?;
+    }
+
+    let now = Utc::now();
+    let view = saved_view::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        owner_id: Set(claims.user_id()),
+        name: Set(payload.name),
+        filters: Set(serde_json::to_value(payload.filters)?),
+        sort: Set(payload.sort.map(|sort| sort.to_string())),
+        columns: Set(serde_json::to_value(payload.columns)?),
+        is_shared: Set(payload.is_shared),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let view = view.insert(&db).await?;
+
+    Ok(Json(view_response(&db, view, &claims).await?))
+}
+
+pub async fn list_views(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<ViewResponse>>, AppError> {
+    let views = SavedView::find()
+        .filter(
+            Condition::any()
+                .add(saved_view::Column::OwnerId.eq(claims.user_id()))
+                .add(saved_view::Column::IsShared.eq(true)),
+        )
+        .order_by_asc(saved_view::Column::Name)
+        .all(&db)
+        .await?;
+
+    Ok(Json(view_responses(&db, views, &claims).await?))
+}
+
+pub async fn get_view(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(view_id): Path<Uuid>,
+) -> Result<Json<ViewResponse>, AppError> {
+    let view = find_visible_view(&db, view_id, &claims).await?;
+
+    Ok(Json(view_response(&db, view, &claims).await?))
+}
+
+pub async fn update_view(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(view_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<UpdateViewRequest>,
+) -> Result<Json<ViewResponse>, AppError> {
+    if payload.is_shared == Some(true) {
+        claims.require_staff()?;
+    }
+    let view = find_owned_view(&db, view_id, &claims).await?;
+
+    let mut view: saved_view::ActiveModel = view.into();
+
+    if let Some(name) = payload.name {
+        view.name = Set(name);
+    }
+    if let Some(filters) = payload.filters {
+        view.filters = Set(serde_json::to_value(filters)?);
+    }
+    if let Some(sort) = payload.sort {
+        view.sort = Set(sort.map(|sort| sort.to_string()));
+    }
+    if let Some(columns) = payload.columns {
+        view.columns = Set(serde_json::to_value(columns)?);
+    }
+    if let Some(is_shared) = payload.is_shared {
+        view.is_shared = Set(is_shared);
+    }
+
+    view.updated_at = Set(Utc::now());
+
+    let view = view.update(&db).await?;
+
+    Ok(Json(view_response(&db, view, &claims).await?))
+}
+
+pub async fn delete_view(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(view_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    let view = find_owned_view(&db, view_id, &claims).await?;
+
+    view.delete(&db).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Runs the view. Opening its first page marks it as seen by the caller, resetting its
+/// `new` count; following the cursor to later pages does not.
+pub async fn get_view_tickets(
+    State(db): State<DatabaseConnection>,
+    Extension(claims): Extension<Claims>,
+    Path(view_id): Path<Uuid>,
+    Query(query): Query<ViewTicketsQuery>,
+) -> Result<Json<Page<TicketListItem>>, AppError> {
+    let view = find_visible_view(&db, view_id, &claims).await?;
+    let filters = resolve_filters(&view, &claims)?;
+    let sort = TicketSort::resolve(view_sort(&view)?, &filters);
+    let page = PageRequest::new(query.cursor.as_deref(), query.page, query.limit)?;
+
+    let tickets = tickets::fetch_tickets(&db, &filters, sort, &page)
+        .await?;
+
+    if page.is_first() {
+        let visit = saved_view_visit::ActiveModel {
+            view_id: Set(view.id),
+            user_id: Set(claims.user_id()),
+            last_viewed_at: Set(Utc::now()),
+        };
+        SavedViewVisit::insert(visit)
+            .on_conflict(
+                OnConflict::columns([saved_view_visit::Column::ViewId, saved_view_visit::Column::UserId])
+                    .update_column(saved_view_visit::Column::LastViewedAt)
+                    .to_owned(),
+            )
+            .exec(&db)
+            .await?;
+    }
+
+    Ok(Json(tickets))
+}
+
+async fn find_visible_view(
+    db: &DatabaseConnection,
+    view_id: Uuid,
+    claims: &Claims,
+) -> Result<saved_view::Model, AppError> {
+    let view = SavedView::find_by_id(view_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if view.owner_id != claims.user_id() && !view.is_shared {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(view)
+}
+
+async fn find_owned_view(
+    db: &DatabaseConnection,
+    view_id: Uuid,
+    claims: &Claims,
+) -> Result<saved_view::Model, AppError> {
+    let view = find_visible_view(db, view_id, claims).await?;
+
+    if view.owner_id != claims.user_id() && !claims.is_admin() {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(view)
+}
+
+async fn view_response(
+    db: &DatabaseConnection,
+    view: saved_view::Model,
+    claims: &Claims,
+) -> Result<ViewResponse, AppError> {
+    let mut responses = view_responses(db, vec![view], claims).await?;
+    Ok(responses.remove(0))
+}
+
+/// Counts for every view in one query: each view's filters become one branch of a
+/// `UNION ALL`, counting both all matches and those updated since the caller's visit.
+async fn view_responses(
+    db: &DatabaseConnection,
+    views: Vec<saved_view::Model>,
+    claims: &Claims,
+) -> Result<Vec<ViewResponse>, AppError> {
+    if views.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let visits: HashMap<Uuid, chrono::DateTime<Utc>> = SavedViewVisit::find()
+        .filter(saved_view_visit::Column::ViewId.is_in(views.iter().map(|view| view.id)))
+        .filter(saved_view_visit::Column::UserId.eq(claims.user_id()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|visit| (visit.view_id, visit.last_viewed_at))
+        .collect();
+
+    let mut branches = Vec::with_capacity(views.len());
+    for view in &views {
+        let filters = resolve_filters(view, claims)?;
+        let new = match visits.get(&view.id) {
+            Some(since) => Expr::cust_with_values("COUNT(*) FILTER (WHERE tickets.updated_at > $1)", [*since]),
+            None => Expr::cust("COUNT(*)"),
+        };
+        branches.push(
+            tickets::filtered_tickets(&filters)
+                .select_only()
+                .column_as(Expr::val(view.id), "view_id")
+                .column_as(Expr::cust("COUNT(*)"), "total")
+                .column_as(new, "new")
+                .into_query(),
+        );
+    }
+    let mut branches = branches.into_iter();
+    let mut query = branches.next().expect("at least one view");
+    for branch in branches {
+        query.union(UnionType::All, branch);
+    }
+
+    let counts: HashMap<Uuid, ViewCounts> = ViewCounts::find_by_statement(db.get_database_backend().build(&query))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|counts| (counts.view_id, counts))
+        .collect();
+
+    Ok(views
+        .into_iter()
+        .map(|view| {
+            let (total, new) = counts
+                .get(&view.id)
+                .map_or((0, 0), |counts| (counts.total as u64, counts.new as u64));
+            ViewResponse {
+                id: view.id,
+                owner_id: view.owner_id,
+                name: view.name,
+                filters: view.filters,
+                sort: view.sort,
+                columns: view.columns,
+                is_shared: view.is_shared,
+                total,
+                new,
+                created_at: view.created_at,
+                updated_at: view.updated_at,
+            }
+        })
+        .collect())
+}
+
+fn resolve_filters(view: &saved_view::Model, claims: &Claims) -> Result<TicketFilters, AppError> {
+    let filters: ViewFilters =
+        serde_json::from_value(view.filters.clone())?;
+
+    let mut resolved = filters.tickets;
+    resolved.include_internal = claims.is_staff();
+    if filters.assigned_to_me {
+        resolved.assigned_to = Some(claims.user_id());
+    }
+
+    Ok(resolved)
+}
+
+fn view_sort(view: &saved_view::Model) -> Result<Option<TicketSort>, AppError> {
+    view.sort
+        .as_deref()
+        .map(|sort| sort.parse().map_err(AppError::bad_request))
+        .transpose()
+}
+
+fn view_columns(columns: &[String]) -> Result<(), ValidationError> {
+    let unknown: Vec<&str> = columns
+        .iter()
+        .map(String::as_str)
+        .filter(|column| !VIEW_COLUMNS.contains(column))
+        .collect();
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("unknown_column");
+        error.message = Some(format!("not ticket fields: {}", unknown.join(", ")).into());
+        Err(error)
+    }
+}
\ No newline at end of file
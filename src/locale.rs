@@ -0,0 +1,78 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+
+use crate::validation::LOCALES;
+
+/// Articles are written in this language unless another is given.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The languages in `Accept-Language`, most preferred first. Only the primary subtag is
+/// kept, so `de-AT` counts as `de`; languages with `q=0` are left out.
+pub fn accepted(headers: &HeaderMap) -> Vec<String> {
+    let Some(value) = headers.get(header::ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok()) else {
+        return Vec::new();
+    };
+
+    let mut languages: Vec<(String, f32)> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let tag = parts.next().filter(|tag| !tag.is_empty())?;
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+            let language = tag.split('-').next().unwrap_or(tag).to_lowercase();
+            (quality > 0.0).then_some((language, quality))
+        })
+        .collect();
+    // Stable, so languages with the same quality keep the caller's order.
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    languages.into_iter().map(|(language, _)| language).collect()
+}
+
+/// The caller's most preferred language among `available`; `*` picks the first of them.
+/// `None` when the caller has no preference that can be met.
+pub fn negotiate<'a>(headers: &HeaderMap, available: &[&'a str]) -> Option<&'a str> {
+    accepted(headers).iter().find_map(|language| {
+        if language == "*" {
+            available.first().copied()
+        } else {
+            available.iter().find(|locale| **locale == language).copied()
+        }
+    })
+}
+
+/// Headers for a response in `locale`. `Vary` makes caches keep one copy per language.
+pub fn response_headers(locale: &str) -> [(HeaderName, HeaderValue); 2] {
+    let locale = LOCALES.iter().find(|supported| **supported == locale).unwrap_or(&DEFAULT_LOCALE);
+
+    [
+        (header::CONTENT_LANGUAGE, HeaderValue::from_static(locale)),
+        (header::VARY, HeaderValue::from_static("accept-language")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(accept_language: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static(accept_language));
+        headers
+    }
+
+    #[test]
+    fn negotiate_picks_the_most_preferred_available_language() {
+        assert_eq!(negotiate(&headers("fr, de-AT;q=0.9, en;q=0.8"), &["en", "de"]), Some("de"));
+        assert_eq!(negotiate(&headers("en;q=0.5, es"), &["en", "es"]), Some("es"));
+        assert_eq!(negotiate(&headers("fr, *;q=0.1"), &["es", "en"]), Some("es"));
+    }
+
+    #[test]
+    fn negotiate_without_a_usable_preference() {
+        assert_eq!(negotiate(&HeaderMap::new(), &["en"]), None);
+        assert_eq!(negotiate(&headers("fr"), &["en"]), None);
+        assert_eq!(negotiate(&headers("de;q=0, fr"), &["de"]), None);
+    }
+}
\ No newline at end of file
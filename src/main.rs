@@ -1,9 +1,19 @@
-use axum::{routing::get, Router};
-use std::net::SocketAddr;
+use axum::{middleware, routing::get, Router};
+use std::{env, net::SocketAddr, process, sync::Arc};
 use dotenv::dotenv;
-use tracing_subscriber;
 
+mod article_files;
+mod cli;
 mod db;
+mod state;
+mod storage;
+mod error;
+mod extract;
+mod locale;
+mod markdown;
+mod pagination;
+mod request_id;
+mod validation;
 mod models;
 mod auth;
 mod handlers;
@@ -12,7 +22,10 @@ mod ws;
 mod email;
 
 use db::get_db_connection;
+use request_id::request_id_middleware;
 use routes::create_router;
+use state::AppState;
+use ws::WsState;
 
 async fn health_check() -> &'static str {
     "OK"
@@ -26,10 +39,26 @@ async fn main() {
     let db = get_db_connection().await;
     tracing::info!("Connected to database");
 
-    
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&db, &args).await {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let storage = storage::from_env().expect("Failed to configure attachment storage");
+    let state = AppState {
+        db,
+        storage,
+        ws: Arc::new(WsState::new()),
+    };
+
     let app = Router::new()
         .route("/health", get(health_check))
-        .merge(create_router(db));
+        .merge(create_router(state))
+        .layer(middleware::from_fn(request_id_middleware));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     tracing::info!("Listening on {}", addr);
@@ -0,0 +1,126 @@
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+/// CommonMark plus GitHub-style tables and strikethrough.
+fn options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH
+}
+
+/// Renders comment Markdown to HTML that is safe to embed in a page or email.
+/// Raw HTML in the source is allowed through the sanitiser's allow-list only, so
+/// scripts, iframes, styles, event handlers and `javascript:` links are removed.
+pub fn to_html(source: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options()));
+    ammonia::clean(&unsafe_html)
+}
+
+/// Renders comment Markdown as readable plain text for the text part of emails.
+/// Links keep their target in parentheses and list items get bullets or numbers.
+pub fn to_plain_text(source: &str) -> String {
+    let mut text = String::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut links: Vec<(String, usize)> = Vec::new();
+
+    for event in Parser::new_ext(source, options()) {
+        match event {
+            Event::Text(value) | Event::Code(value) => text.push_str(&value),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => {
+                text.push_str("---");
+                end_block(&mut text, &lists);
+            }
+            Event::Start(Tag::List(start)) => {
+                if !lists.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                end_block(&mut text, &lists);
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{number}. "));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push((dest_url.to_string(), text.len())),
+            Event::End(TagEnd::Link) => {
+                if let Some((url, start)) = links.pop() {
+                    if text[start..] != url {
+                        text.push_str(&format!(" ({url})"));
+                    }
+                }
+            }
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                text.pop();
+                text.push('\n');
+            }
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::BlockQuote(_) | TagEnd::Table,
+            ) => end_block(&mut text, &lists),
+            _ => {}
+        }
+    }
+
+    text.trim_end().to_string()
+}
+
+/// Escapes `text` so it renders literally, e.g. as the text of a link. CommonMark
+/// allows a backslash before any ASCII punctuation.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Blocks are separated by a blank line, except inside lists where items stay tight.
+fn end_block(text: &mut String, lists: &[Option<u64>]) {
+    while text.ends_with(' ') {
+        text.pop();
+    }
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    if lists.is_empty() && !text.ends_with("\n\n") {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_plain_text_keeps_structure_readable() {
+        let source = "Hello **world**\n\n1. one\n2. [docs](https://example.com/docs)\n   - nested\n\n---\n\n`code`";
+        assert_eq!(
+            to_plain_text(source),
+            "Hello world\n\n1. one\n2. docs (https://example.com/docs)\n  - nested\n\n---\n\ncode",
+        );
+    }
+
+    #[test]
+    fn to_plain_text_does_not_repeat_bare_links() {
+        assert_eq!(to_plain_text("<https://example.com>"), "https://example.com");
+    }
+
+    #[test]
+    fn escaped_text_renders_literally() {
+        let text = "*not bold* [or a link](x) <b>";
+        assert_eq!(escape("a_b"), "a\\_b");
+        assert_eq!(to_plain_text(&escape(text)), text);
+    }
+}
\ No newline at end of file
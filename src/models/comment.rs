@@ -12,6 +12,12 @@ pub struct Model {
     pub content: String,
     pub is_internal: bool,
     pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<Uuid>,
+    pub parent_comment_id: Option<Uuid>,
+    /// `Message-ID` of the email the comment arrived in, for matching later replies.
+    pub email_message_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "csat_surveys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    /// The ticket's assignee when it was resolved.
+    pub agent_id: Option<Uuid>,
+    pub customer_id: Uuid,
+    /// 1 to 5; `None` until the customer answers.
+    pub rating: Option<i16>,
+    pub comment: Option<String>,
+    pub sent_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
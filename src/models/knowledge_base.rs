@@ -9,9 +9,27 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
     pub title: String,
+    /// Unique, lowercase letters, digits and hyphens.
+    pub slug: String,
     pub content: String,
     pub category: String,
     pub tags: Value,
+    /// Number of the latest revision.
+    pub revision: i32,
+    /// `draft`, `in_review`, `published` or `archived`.
+    pub status: String,
+    pub reviewer_id: Option<Uuid>,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<DateTime<Utc>>,
+    /// A published article is only visible from this time, when set.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// A published article stops being visible at this time, when set.
+    pub unpublish_at: Option<DateTime<Utc>>,
+    /// `public` (anyone, including the help center), `customers` (signed-in users) or
+    /// `internal` (staff only).
+    pub visibility: String,
+    /// Language the article is written in: `en`, `de` or `es`.
+    pub locale: String,
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
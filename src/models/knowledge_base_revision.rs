@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// A full copy of an article as saved by one edit.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "knowledge_base_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub article_id: Uuid,
+    /// Numbered from 1 per article.
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    pub category: String,
+    pub tags: Value,
+    pub edited_by: Uuid,
+    /// Set when this revision was created by rolling back to an earlier one.
+    pub restored_from: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
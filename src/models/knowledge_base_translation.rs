@@ -0,0 +1,29 @@
+use sea_orm::entity::prelude::*;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "knowledge_base_translations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub article_id: Uuid,
+    /// One of the supported locales other than the article's own.
+    pub locale: String,
+    pub title: String,
+    pub content: String,
+    /// `draft` or `published`; only published translations are shown to readers.
+    pub status: String,
+    /// Revision of the article the translation was made from.
+    pub source_revision: i32,
+    /// The article's title or content has changed since `source_revision`.
+    pub outdated: bool,
+    pub translated_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
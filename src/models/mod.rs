@@ -1,9 +1,39 @@
 pub mod user;
 pub mod ticket;
 pub mod comment;
+pub mod comment_revision;
+pub mod comment_mention;
+pub mod attachment;
 pub mod knowledge_base;
+pub mod knowledge_base_revision;
+pub mod knowledge_base_slug_redirect;
+pub mod knowledge_base_translation;
+pub mod knowledge_base_view;
+pub mod knowledge_base_vote;
+pub mod knowledge_base_search_miss;
+pub mod saved_view;
+pub mod saved_view_visit;
+pub mod ticket_macro;
+pub mod macro_application;
+pub mod csat_survey;
+pub mod ticket_article;
 
 pub use user::Entity as User;
 pub use ticket::Entity as Ticket;
 pub use comment::Entity as Comment;
-pub use knowledge_base::Entity as KnowledgeBase; 
\ No newline at end of file
+pub use comment_revision::Entity as CommentRevision;
+pub use comment_mention::Entity as CommentMention;
+pub use attachment::Entity as Attachment;
+pub use knowledge_base::Entity as KnowledgeBase;
+pub use knowledge_base_revision::Entity as KnowledgeBaseRevision;
+pub use knowledge_base_slug_redirect::Entity as KnowledgeBaseSlugRedirect;
+pub use knowledge_base_translation::Entity as KnowledgeBaseTranslation;
+pub use knowledge_base_view::Entity as KnowledgeBaseView;
+pub use knowledge_base_vote::Entity as KnowledgeBaseVote;
+pub use knowledge_base_search_miss::Entity as KnowledgeBaseSearchMiss;
+pub use saved_view::Entity as SavedView;
+pub use saved_view_visit::Entity as SavedViewVisit;
+pub use ticket_macro::Entity as Macro;
+pub use macro_application::Entity as MacroApplication;
+pub use csat_survey::Entity as CsatSurvey;
+pub use ticket_article::Entity as TicketArticle;
\ No newline at end of file
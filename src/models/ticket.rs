@@ -1,6 +1,7 @@
 use sea_orm::entity::prelude::*;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use serde_json::Value;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "tickets")]
@@ -12,6 +13,7 @@ pub struct Model {
     pub status: String,
     pub priority: String,
     pub assigned_to: Option<Uuid>,
+    pub tags: Value,
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
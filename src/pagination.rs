@@ -0,0 +1,208 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sea_orm::{
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select, Value, Order, sea_query::Expr
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Response envelope shared by every list endpoint.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` to fetch the next page; `null` on the last page.
+    pub next_cursor: Option<String>,
+    /// Number of rows matching the filters, independent of the cursor.
+    pub total: u64,
+}
+
+/// Position after the last row of a page: that row's sort key (as Postgres text) and id,
+/// along with the ordering they belong to. Encoded as URL-safe base64 JSON so clients
+/// treat it as opaque.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub sort: String,
+    pub descending: bool,
+    pub key: String,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor serializes");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(encoded: &str) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+/// Where a page starts and how many rows it holds.
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub cursor: Option<Cursor>,
+    /// Legacy `page=` offset, only used when no cursor is given.
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl PageRequest {
+    /// Builds a request from the common `cursor`, `page` and `limit` query parameters.
+    /// `limit` is clamped to `1..=MAX_PAGE_SIZE`; `page` counts from 1 and `page=0` is
+    /// treated as the first page.
+    pub fn new(cursor: Option<&str>, page: Option<u64>, limit: Option<u64>) -> Result<Self, AppError> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        match cursor {
+            Some(cursor) => Ok(PageRequest {
+                cursor: Some(Cursor::decode(cursor).ok_or_else(|| AppError::bad_request("invalid cursor"))?),
+                offset: 0,
+                limit,
+            }),
+            None => Ok(PageRequest {
+                cursor: None,
+                offset: page.unwrap_or(1).saturating_sub(1).saturating_mul(limit),
+                limit,
+            }),
+        }
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.cursor.is_none() && self.offset == 0
+    }
+}
+
+/// Splits a `sort=` value such as `-updated_at` into the field name and whether it is descending.
+pub fn parse_sort(sort: &str) -> (&str, bool) {
+    match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    }
+}
+
+/// A keyset ordering over an SQL expression, with the row id as tiebreaker.
+///
+/// `sort` names the field being ordered by and is recorded in cursors. `expr` may
+/// reference `values` as `$1..$n`; `cast` is the Postgres type the text cursor key is
+/// cast back to when comparing.
+pub struct Keyset {
+    pub sort: &'static str,
+    pub expr: String,
+    pub values: Vec<Value>,
+    pub cast: &'static str,
+    pub id_column: &'static str,
+    pub descending: bool,
+}
+
+impl Keyset {
+    /// Orders `select` by the keyset, selects the key as the `sort_key` column and,
+    /// when resuming from a cursor, skips everything up to and including it. A cursor
+    /// from a different ordering is rejected, as its key would be cast to the wrong type.
+    pub fn apply<E: EntityTrait>(&self, select: Select<E>, cursor: Option<&Cursor>) -> Result<Select<E>, AppError> {
+        let order = if self.descending { Order::Desc } else { Order::Asc };
+        let id_column = self.id_column;
+
+        let mut select = select.column_as(
+            Expr::cust_with_values(format!("({})::text", self.expr), self.values.clone()),
+            "sort_key",
+        );
+
+        if let Some(cursor) = cursor {
+            if cursor.sort != self.sort || cursor.descending != self.descending {
+                return Err(AppError::bad_request("cursor does not match sort"));
+            }
+
+            let key_param = self.values.len() + 1;
+            let id_param = key_param + 1;
+            let comparison = if self.descending { "<" } else { ">" };
+
+            let mut values = self.values.clone();
+            values.push(cursor.key.clone().into());
+            values.push(cursor.id.into());
+
+            select = select.filter(Expr::cust_with_values(
+                format!(
+                    "(({}), {id_column}) {comparison} (${key_param}::{}, ${id_param})",
+                    self.expr, self.cast
+                ),
+                values,
+            ));
+        }
+
+        Ok(select
+            .order_by(Expr::cust_with_values(self.expr.clone(), self.values.clone()), order.clone())
+            .order_by(Expr::cust(id_column), order))
+    }
+
+    /// The cursor resuming after the row with this sort key and id.
+    pub fn cursor(&self, key: &str, id: Uuid) -> Cursor {
+        Cursor {
+            sort: self.sort.to_string(),
+            descending: self.descending,
+            key: key.to_string(),
+            id,
+        }
+    }
+}
+
+/// Turns `limit + 1` fetched rows into a page, using the extra row only to decide
+/// whether there is a next page.
+pub fn into_page<R, T>(
+    mut rows: Vec<R>,
+    request: &PageRequest,
+    total: u64,
+    cursor_of: impl Fn(&R) -> Cursor,
+    item_of: impl FnMut(R) -> T,
+) -> Page<T> {
+    let has_more = rows.len() as u64 > request.limit;
+    rows.truncate(request.limit as usize);
+
+    let next_cursor = if has_more {
+        rows.last().map(|row| cursor_of(row).encode())
+    } else {
+        None
+    };
+
+    Page {
+        items: rows.into_iter().map(item_of).collect(),
+        next_cursor,
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor {
+            sort: "created_at".to_string(),
+            descending: true,
+            key: "2024-02-01T10:00:00+00:00".to_string(),
+            id: Uuid::new_v4(),
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn cursor_rejects_malformed_input() {
+        assert_eq!(Cursor::decode("not a cursor"), None);
+        assert_eq!(Cursor::decode(&URL_SAFE_NO_PAD.encode("{\"sort\":\"title\"}")), None);
+    }
+
+    #[test]
+    fn page_request_uses_offsets_without_a_cursor() {
+        let page = PageRequest::new(None, Some(3), Some(10)).unwrap();
+        assert_eq!((page.offset, page.limit), (20, 10));
+        assert!(PageRequest::new(None, Some(0), None).unwrap().is_first());
+        assert!(matches!(PageRequest::new(Some("%%%"), None, None), Err(AppError::BadRequest(_))));
+    }
+}
\ No newline at end of file
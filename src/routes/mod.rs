@@ -1,17 +1,17 @@
 use axum::{
-    routing::{get, post, put, delete},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post, put, patch, delete},
     Router,
 };
-use sea_orm::DatabaseConnection;
+use crate::auth::middleware::auth_middleware;
+use crate::handlers::{auth, tickets, comments, inbound_email, csat, attachments, mentions, macros, knowledge_base, knowledge_base_analytics, knowledge_base_files, knowledge_base_translations, help_center, ticket_articles, views, users};
+use crate::state::AppState;
+use crate::ws;
+use crate::validation::{MAX_IMPORT_BYTES, MAX_UPLOAD_BYTES};
 
-use crate::handlers::{auth, tickets, comments, knowledge_base};
-
-pub fn create_router(db: DatabaseConnection) -> Router {
-    Router::new()
-        
-        .route("/auth/login", post(auth::login))
-        .route("/auth/register", post(auth::register))
-       
+pub fn create_router(state: AppState) -> Router {
+    let protected = Router::new()
         .route("/tickets", get(tickets::list_tickets))
         .route("/tickets", post(tickets::create_ticket))
         .route("/tickets/:id", get(tickets::get_ticket))
@@ -19,10 +19,87 @@ pub fn create_router(db: DatabaseConnection) -> Router {
         .route("/tickets/:id", delete(tickets::delete_ticket))
         .route("/tickets/:id/comments", get(comments::get_ticket_comments))
         .route("/tickets/:id/comments", post(comments::create_comment))
+        .route("/tickets/:id/comments/:comment_id", patch(comments::update_comment))
+        .route("/tickets/:id/comments/:comment_id", delete(comments::delete_comment))
+        .route("/tickets/:id/comments/:comment_id/revisions", get(comments::get_comment_revisions))
+        .route("/tickets/:id/macros/:macro_id", post(macros::apply_macro))
+        .route("/tickets/:id/suggested-articles", get(knowledge_base::get_suggested_articles))
+        .route("/tickets/:id/articles", get(ticket_articles::list_ticket_articles))
+        .route("/tickets/:id/articles", post(ticket_articles::link_article))
+        .route("/tickets/:id/articles/:article_id", delete(ticket_articles::unlink_article))
+        .route("/tickets/:id/attachments", get(attachments::get_ticket_attachments))
+        .route(
+            "/tickets/:id/attachments",
+            post(attachments::upload_ticket_attachments).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route(
+            "/tickets/:id/comments/:comment_id/attachments",
+            post(attachments::upload_comment_attachments).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/attachments/:id", get(attachments::download_attachment))
+        .route("/attachments/:id", delete(attachments::delete_attachment))
         .route("/knowledge-base", get(knowledge_base::list_articles))
         .route("/knowledge-base", post(knowledge_base::create_article))
+        .route("/knowledge-base/suggest", post(knowledge_base::suggest_articles))
+        .route("/knowledge-base/usage", get(ticket_articles::get_article_usage))
+        .route("/knowledge-base/categories", get(knowledge_base::list_categories))
+        .route("/knowledge-base/by-slug/:slug", get(knowledge_base::get_article_by_slug))
+        .route(
+            "/knowledge-base/import",
+            post(knowledge_base_files::import_articles).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route("/knowledge-base/export", get(knowledge_base_files::export_articles))
+        .route("/knowledge-base/analytics/most-viewed", get(knowledge_base_analytics::get_most_viewed))
+        .route("/knowledge-base/analytics/least-helpful", get(knowledge_base_analytics::get_least_helpful))
+        .route("/knowledge-base/analytics/failed-searches", get(knowledge_base_analytics::get_failed_searches))
         .route("/knowledge-base/:id", get(knowledge_base::get_article))
         .route("/knowledge-base/:id", put(knowledge_base::update_article))
         .route("/knowledge-base/:id", delete(knowledge_base::delete_article))
-        .with_state(db)
-} 
\ No newline at end of file
+        .route("/knowledge-base/:id/status", post(knowledge_base::update_article_status))
+        .route("/knowledge-base/:id/revisions", get(knowledge_base::list_revisions))
+        .route("/knowledge-base/:id/revisions/:revision", get(knowledge_base::get_revision))
+        .route("/knowledge-base/:id/revisions/:revision/restore", post(knowledge_base::restore_revision))
+        .route("/knowledge-base/:id/diff", get(knowledge_base::diff_revisions))
+        .route("/knowledge-base/:id/votes", get(knowledge_base_analytics::list_article_votes))
+        .route("/knowledge-base/:id/votes", post(knowledge_base_analytics::vote_article))
+        .route("/knowledge-base/:id/translations", get(knowledge_base_translations::list_translations))
+        .route("/knowledge-base/:id/translations/:locale", get(knowledge_base_translations::get_translation))
+        .route("/knowledge-base/:id/translations/:locale", put(knowledge_base_translations::put_translation))
+        .route("/knowledge-base/:id/translations/:locale", delete(knowledge_base_translations::delete_translation))
+        .route("/macros", get(macros::list_macros))
+        .route("/macros", post(macros::create_macro))
+        .route("/macros/usage", get(macros::get_macro_usage))
+        .route("/macros/:id", get(macros::get_macro))
+        .route("/macros/:id", put(macros::update_macro))
+        .route("/macros/:id", delete(macros::delete_macro))
+        .route("/csat/summary", get(csat::get_csat_summary))
+        .route("/users/:id/role", put(users::update_user_role))
+        .route("/views", get(views::list_views))
+        .route("/views", post(views::create_view))
+        .route("/views/:id", get(views::get_view))
+        .route("/views/:id", put(views::update_view))
+        .route("/views/:id", delete(views::delete_view))
+        .route("/views/:id/tickets", get(views::get_view_tickets))
+        .route("/me/mentions", get(mentions::list_my_mentions))
+        .route("/me/mentions/read", post(mentions::mark_all_mentions_read))
+        .route("/me/mentions/:id", patch(mentions::update_mention))
+        .route_layer(middleware::from_fn_with_state(state.db.clone(), auth_middleware));
+
+    Router::new()
+        .route("/auth/login", post(auth::login))
+        .route("/auth/register", post(auth::register))
+        .route(
+            "/inbound/email",
+            post(inbound_email::receive_email).route_layer(middleware::from_fn(inbound_email::require_secret)),
+        )
+        .route("/csat", get(csat::get_survey))
+        .route("/csat", post(csat::submit_survey))
+        .route("/help-center/articles", get(help_center::list_help_center_articles))
+        .route("/help-center/articles/:id", get(help_center::get_help_center_article))
+        .route("/help-center/articles/by-slug/:slug", get(help_center::get_help_center_article_by_slug))
+        .route("/help-center/categories", get(help_center::list_help_center_categories))
+        .route("/help-center/articles/:id/votes", post(knowledge_base_analytics::vote_help_center_article))
+        .route("/ws", get(ws::ws_handler))
+        .merge(protected)
+        .with_state(state)
+}
\ No newline at end of file
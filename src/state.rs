@@ -0,0 +1,33 @@
+use axum::extract::FromRef;
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+
+use crate::storage::StorageBackend;
+use crate::ws::WsState;
+
+/// Shared router state. Handlers extract just the part they need, e.g.
+/// `State<DatabaseConnection>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub storage: Arc<dyn StorageBackend>,
+    pub ws: Arc<WsState>,
+}
+
+impl FromRef<AppState> for DatabaseConnection {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn StorageBackend> {
+    fn from_ref(state: &AppState) -> Self {
+        state.storage.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<WsState> {
+    fn from_ref(state: &AppState) -> Self {
+        state.ws.clone()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use axum::body::Bytes;
+use std::{env, sync::Arc};
+
+pub mod local;
+pub mod s3;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("object not found")]
+    NotFound,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("storage backend error: {0}")]
+    Backend(String),
+    #[error("storage is misconfigured: {0}")]
+    Config(String),
+}
+
+/// Where attachment bytes live. Keys are generated by the server and never contain
+/// user input.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), StorageError>;
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
+
+    /// Deleting a missing object is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Builds the backend selected by `STORAGE_BACKEND` (`local`, the default, or `s3`).
+pub fn from_env() -> Result<Arc<dyn StorageBackend>, StorageError> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" => {
+            let root = env::var("STORAGE_PATH").unwrap_or_else(|_| "./data/attachments".to_string());
+            Ok(Arc::new(LocalStorage::new(root)))
+        }
+        "s3" => Ok(Arc::new(S3Storage::from_env()?)),
+        other => Err(StorageError::Config(format!("unknown STORAGE_BACKEND `{other}`"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn round_trip(storage: &dyn StorageBackend) {
+        let key = format!("tests/{}", uuid::Uuid::new_v4());
+        let data = Bytes::from_static(b"hello, storage");
+
+        storage.put(&key, "text/plain", data.clone()).await.unwrap();
+        assert_eq!(storage.get(&key).await.unwrap(), data);
+
+        storage.delete(&key).await.unwrap();
+        assert!(matches!(storage.get(&key).await, Err(StorageError::NotFound)));
+        storage.delete(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn local_storage_round_trip() {
+        let root = env::temp_dir().join(format!("storage-test-{}", uuid::Uuid::new_v4()));
+        round_trip(&LocalStorage::new(&root)).await;
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    /// Needs a bucket on an S3-compatible store such as MinIO, configured with
+    /// `S3_ENDPOINT`, `S3_BUCKET`, `S3_ACCESS_KEY_ID` and `S3_SECRET_ACCESS_KEY`.
+    #[tokio::test]
+    #[ignore = "needs an S3-compatible store at S3_ENDPOINT"]
+    async fn s3_storage_round_trip() {
+        assert!(env::var("S3_ENDPOINT").is_ok(), "S3_ENDPOINT must be set");
+        round_trip(&S3Storage::from_env().unwrap()).await;
+    }
+}
\ No newline at end of file
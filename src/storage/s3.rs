@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    config::{BehaviorVersion, Credentials, Region},
+    error::DisplayErrorContext,
+    primitives::ByteStream,
+    Client,
+};
+use axum::body::Bytes;
+use std::env;
+
+use super::{StorageBackend, StorageError};
+
+/// Any S3-compatible object store. Setting `S3_ENDPOINT` points it at MinIO or a
+/// similar stand-in, which also switches to path-style bucket addressing.
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub fn from_env() -> Result<Self, StorageError> {
+        let var = |name: &str| env::var(name).map_err(|_| StorageError::Config(format!("{name} must be set")));
+
+        let bucket = var("S3_BUCKET")?;
+        let region = env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let endpoint = env::var("S3_ENDPOINT").ok();
+        let credentials = Credentials::new(
+            var("S3_ACCESS_KEY_ID")?,
+            var("S3_SECRET_ACCESS_KEY")?,
+            None,
+            None,
+            "environment",
+        );
+
+        let mut config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(region))
+            .credentials_provider(credentials)
+            .force_path_style(endpoint.is_some());
+        if let Some(endpoint) = endpoint {
+            config = config.endpoint_url(endpoint);
+        }
+
+        Ok(S3Storage {
+            client: Client::from_conf(config.build()),
+            bucket,
+        })
+    }
+}
+
+fn backend_error(error: impl std::error::Error) -> StorageError {
+    StorageError::Backend(DisplayErrorContext(error).to_string())
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), StorageError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let object = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(service) if service.is_no_such_key() => StorageError::NotFound,
+                _ => backend_error(e),
+            })?;
+
+        let data = object.body.collect().await.map_err(backend_error)?;
+        Ok(data.into_bytes())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
\ No newline at end of file
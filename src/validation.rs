@@ -0,0 +1,199 @@
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::error::{AppError, FieldError};
+
+// Column limits from the migrations, shared by the request types' `#[validate]` attributes.
+pub const MAX_NAME_LEN: u64 = 255;
+pub const MAX_EMAIL_LEN: u64 = 255;
+pub const MAX_SUBJECT_LEN: u64 = 500;
+pub const MAX_TITLE_LEN: u64 = 500;
+pub const MAX_CATEGORY_LEN: u64 = 100;
+pub const MAX_SLUG_LEN: usize = 255;
+/// `TEXT` columns are unbounded; this keeps single requests to a sane size.
+pub const MAX_BODY_LEN: u64 = 50_000;
+/// bcrypt silently ignores everything past 72 bytes.
+pub const MIN_PASSWORD_LEN: u64 = 8;
+pub const MAX_PASSWORD_LEN: u64 = 72;
+
+pub const MAX_TAGS: usize = 20;
+pub const MAX_TAG_LEN: usize = 50;
+
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+pub const MAX_ATTACHMENTS_PER_UPLOAD: usize = 5;
+/// Body limit for upload routes: a full batch plus room for the multipart framing.
+pub const MAX_UPLOAD_BYTES: usize = MAX_ATTACHMENTS_PER_UPLOAD * MAX_ATTACHMENT_BYTES + 64 * 1024;
+/// Body limit for knowledge base imports, compressed.
+pub const MAX_IMPORT_BYTES: usize = 20 * 1024 * 1024;
+
+pub const PRIORITIES: &[&str] = &["Low", "Medium", "High", "Critical"];
+pub const STATUSES: &[&str] = &["Open", "In Progress", "Pending", "Resolved", "Closed"];
+pub const ROLES: &[&str] = &["customer", "agent", "kb_editor", "admin"];
+/// The only role `POST /auth/register` creates.
+pub const CUSTOMER_ROLE: &str = "customer";
+pub const ARTICLE_STATUSES: &[&str] = &["draft", "in_review", "published", "archived"];
+pub const ARTICLE_VISIBILITIES: &[&str] = &["public", "customers", "internal"];
+pub const TRANSLATION_STATUSES: &[&str] = &["draft", "published"];
+/// Languages articles can be written and translated in.
+pub const LOCALES: &[&str] = &["en", "de", "es"];
+
+fn error(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message.into());
+    error
+}
+
+fn one_of(value: &str, allowed: &[&str]) -> Result<(), ValidationError> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(error("one_of", format!("must be one of: {}", allowed.join(", "))))
+    }
+}
+
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(error("blank", "must not be blank"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn priority(value: &str) -> Result<(), ValidationError> {
+    one_of(value, PRIORITIES)
+}
+
+pub fn status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, STATUSES)
+}
+
+pub fn role(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ROLES)
+}
+
+pub fn article_status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ARTICLE_STATUSES)
+}
+
+pub fn article_visibility(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ARTICLE_VISIBILITIES)
+}
+
+pub fn translation_status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, TRANSLATION_STATUSES)
+}
+
+pub fn locale(value: &str) -> Result<(), ValidationError> {
+    one_of(value, LOCALES)
+}
+
+/// Lowercase ASCII letters and digits in hyphen-separated words, like `reset-your-password`.
+pub fn slug(value: &str) -> Result<(), ValidationError> {
+    let valid = !value.is_empty()
+        && value.len() <= MAX_SLUG_LEN
+        && value.split('-').all(|word| {
+            !word.is_empty() && word.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(error(
+            "slug",
+            format!("must be at most {MAX_SLUG_LEN} lowercase letters, digits and single hyphens"),
+        ))
+    }
+}
+
+/// Length is checked separately; this requires at least one letter and one digit.
+pub fn password_strength(value: &str) -> Result<(), ValidationError> {
+    let has_letter = value.chars().any(char::is_alphabetic);
+    let has_digit = value.chars().any(|c| c.is_ascii_digit());
+
+    if has_letter && has_digit {
+        Ok(())
+    } else {
+        Err(error("password_strength", "must contain at least one letter and one digit"))
+    }
+}
+
+pub fn tags(values: &[String]) -> Result<(), ValidationError> {
+    if values.len() > MAX_TAGS {
+        return Err(error("too_many", format!("at most {MAX_TAGS} tags are allowed")));
+    }
+    if values
+        .iter()
+        .any(|tag| tag.trim().is_empty() || tag.chars().count() > MAX_TAG_LEN)
+    {
+        return Err(error("tag_length", format!("each tag must be 1 to {MAX_TAG_LEN} characters")));
+    }
+    Ok(())
+}
+
+/// For update fields that can be cleared, with `#[serde(default)]`: a missing field is
+/// `None` and leaves the value alone, `null` is `Some(None)` and clears it.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Human-readable fallback for the built-in validators, which carry no message.
+fn describe(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let param = |name: &str| error.params.get(name).map(|value| value.to_string());
+    match error.code.as_ref() {
+        "length" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("must be between {min} and {max} characters"),
+            (Some(min), None) => format!("must be at least {min} characters"),
+            (None, Some(max)) => format!("must be at most {max} characters"),
+            (None, None) => "has an invalid length".to_string(),
+        },
+        "email" => "must be a valid email address".to_string(),
+        "range" => "is out of range".to_string(),
+        code => format!("failed the `{code}` check"),
+    }
+}
+
+fn collect(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => out.extend(
+                field_errors
+                    .iter()
+                    .map(|error| FieldError::new(path.clone(), error.code.clone(), describe(error))),
+            ),
+            ValidationErrorsKind::Struct(nested) => collect(nested, &path, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect(nested, &format!("{path}[{index}]"), out);
+                }
+            }
+        }
+    }
+}
+
+/// The failing fields, named `<prefix>.<field>` when a prefix is given.
+pub fn field_errors(errors: &ValidationErrors, prefix: &str) -> Vec<FieldError> {
+    let mut fields = Vec::new();
+    collect(errors, prefix, &mut fields);
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    fields
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::Validation(field_errors(&errors, ""))
+    }
+}
\ No newline at end of file
@@ -1,60 +1,241 @@
 use axum::{
-    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, State},
-    response::IntoResponse,
+    extract::{ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade}, State},
+    response::{IntoResponse, Response},
 };
-use sea_orm::DatabaseConnection;
+use sea_orm::{DatabaseConnection, EntityTrait, QuerySelect};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::broadcast::{self, error::RecvError}, time::timeout};
 use uuid::Uuid;
-use futures_util::{StreamExt, SinkExt};
+use futures_util::{stream::SplitStream, StreamExt, SinkExt};
+use chrono::Utc;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::auth::{verify_token, Claims};
+use crate::error::AppError;
+use crate::extract::Query;
+use crate::models::{Ticket, User, ticket};
+use crate::handlers::{comments::CommentResponse, tickets::TicketResponse};
+
+/// How long a connection opened without `?token=` has to send its `Auth` message.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events sent to clients, as JSON objects with a `type` field naming the variant.
+/// Tickets and comments are sent as the REST API returns them. Every event carries who
+/// opened its ticket, so connections can filter without a query; `requester` is not sent.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
 pub enum WsMessage {
-    TicketUpdate { ticket_id: Uuid, data: String },
-    TypingIndicator { ticket_id: Uuid, user_id: Uuid, is_typing: bool },
-    NewComment { ticket_id: Uuid, comment: String },
+    TicketCreated { ticket: TicketResponse },
+    TicketUpdated { ticket: TicketResponse },
+    TicketDeleted { ticket_id: Uuid, created_by: Uuid },
+    TypingIndicator {
+        ticket_id: Uuid,
+        #[serde(skip)]
+        requester: Uuid,
+        user_id: Uuid,
+        is_typing: bool,
+    },
+    NewComment {
+        ticket_id: Uuid,
+        #[serde(skip)]
+        requester: Uuid,
+        comment: CommentResponse,
+    },
+    Mention {
+        ticket_id: Uuid,
+        #[serde(skip)]
+        requester: Uuid,
+        comment_id: Uuid,
+        mentioned_user_id: Uuid,
+        mentioned_by: Uuid,
+    },
+}
+
+/// Everything a client may send. `user_id` in a typing indicator is taken from the
+/// token, so one user cannot appear to type as another.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    Auth { token: String },
+    TypingIndicator { ticket_id: Uuid, is_typing: bool },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    pub token: Option<String>,
+}
+
+impl WsMessage {
+    /// Who opened the ticket the message is about.
+    pub fn requester(&self) -> Uuid {
+        match self {
+            WsMessage::TicketCreated { ticket } | WsMessage::TicketUpdated { ticket } => ticket.created_by,
+            WsMessage::TicketDeleted { created_by, .. } => *created_by,
+            WsMessage::TypingIndicator { requester, .. }
+            | WsMessage::NewComment { requester, .. }
+            | WsMessage::Mention { requester, .. } => *requester,
+        }
+    }
+
+    /// Whether this message may be delivered to `viewer`: staff see every ticket and
+    /// customers those they opened.
+    pub fn visible_to(&self, viewer: &Claims) -> bool {
+        if !viewer.is_staff() && viewer.user_id() != self.requester() {
+            return false;
+        }
+        match self {
+            WsMessage::NewComment { comment, .. } if comment.is_internal => viewer.is_staff(),
+            WsMessage::Mention { mentioned_user_id, .. } => viewer.user_id() == *mentioned_user_id,
+            // Nobody needs to hear about their own typing.
+            WsMessage::TypingIndicator { user_id, .. } => viewer.user_id() != *user_id,
+            WsMessage::TicketCreated { .. }
+            | WsMessage::TicketUpdated { .. }
+            | WsMessage::TicketDeleted { .. }
+            | WsMessage::NewComment { .. } => true,
+        }
+    }
 }
 
 pub struct WsState {
     pub tx: broadcast::Sender<WsMessage>,
 }
 
+impl WsState {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(256);
+        WsState { tx }
+    }
+
+    /// Sends to every connected socket; each connection filters what its user may see.
+    /// Having no connected sockets is not an error.
+    pub fn publish(&self, message: WsMessage) {
+        let _ = self.tx.send(message);
+    }
+}
+
+/// Browsers cannot set headers on a WebSocket, so the token is passed as `?token=` or
+/// in an `Auth` message sent first. An invalid `?token=` is refused before upgrading;
+/// a missing or invalid `Auth` message closes the socket.
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
+    State(db): State<DatabaseConnection>,
     State(state): State<Arc<WsState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    Query(query): Query<WsQuery>,
+) -> Result<Response, AppError> {
+    let claims = match query.token {
+        Some(token) => Some(authenticate(&db, &token).await.ok_or(AppError::Unauthorized)?),
+        None => None,
+    };
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, db, state, claims)).into_response())
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<WsState>) {
-    let mut rx = state.tx.subscribe();
-    
+/// Clients may only send typing indicators, for tickets they can see. Anything else
+/// closes the socket, as does the token expiring.
+async fn handle_socket(socket: WebSocket, db: DatabaseConnection, state: Arc<WsState>, claims: Option<Claims>) {
     let (mut sender, mut receiver) = socket.split();
-    
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Text(text) = msg {
-                if let Ok(ws_msg) = serde_json::from_str::<WsMessage>(&text) {
-                    let _ = state.tx.send(ws_msg);
+
+    let claims = match claims {
+        Some(claims) => Some(claims),
+        None => timeout(AUTH_TIMEOUT, wait_for_auth(&db, &mut receiver)).await.ok().flatten(),
+    };
+    let Some(claims) = claims else {
+        let _ = sender.send(close(close_code::POLICY, "authentication required")).await;
+        return;
+    };
+
+    let mut rx = state.tx.subscribe();
+    let expires_in = Duration::from_secs((claims.exp - Utc::now().timestamp()).max(0) as u64);
+    let expiry = tokio::time::sleep(expires_in);
+    tokio::pin!(expiry);
+
+    let closing = loop {
+        tokio::select! {
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::TypingIndicator { ticket_id, is_typing }) => {
+                        // Dropped rather than refused, so clients cannot probe for tickets.
+                        if let Some(requester) = typing_requester(&db, &claims, ticket_id).await {
+                            state.publish(WsMessage::TypingIndicator {
+                                ticket_id,
+                                requester,
+                                user_id: claims.user_id(),
+                                is_typing,
+                            });
+                        }
+                    }
+                    Ok(ClientMessage::Auth { .. }) => break Some(close(close_code::POLICY, "already authenticated")),
+                    Err(_) => break Some(close(close_code::POLICY, "only TypingIndicator messages may be sent")),
+                },
+                Some(Ok(Message::Binary(_))) => break Some(close(close_code::UNSUPPORTED, "only text messages are accepted")),
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break None,
+            },
+            event = rx.recv() => match event {
+                Ok(message) => {
+                    if !message.visible_to(&claims) {
+                        continue;
+                    }
+                    let Ok(text) = serde_json::to_string(&message) else { continue };
+                    if sender.send(Message::Text(text)).await.is_err() {
+                        break None;
+                    }
                 }
-            }
+                // A slow client misses the events it fell behind on but stays connected.
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break None,
+            },
+            _ = &mut expiry => break Some(close(close_code::POLICY, "token expired")),
         }
-    });
+    };
 
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if let Ok(text) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(text)).await.is_err() {
-                    break;
-                }
+    if let Some(message) = closing {
+        let _ = sender.send(message).await;
+    }
+}
+
+/// Waits for the `Auth` message of a connection opened without `?token=`.
+async fn wait_for_auth(db: &DatabaseConnection, receiver: &mut SplitStream<WebSocket>) -> Option<Claims> {
+    loop {
+        match receiver.next().await? {
+            Ok(Message::Text(text)) => {
+                let ClientMessage::Auth { token } = serde_json::from_str(&text).ok()? else {
+                    return None;
+                };
+                return authenticate(db, &token).await;
             }
+            Ok(Message::Ping(_) | Message::Pong(_)) => {}
+            _ => return None,
         }
-    });
+    }
+}
 
-    tokio::select! {
-        _ = (&mut recv_task) => send_task.abort(),
-        _ = (&mut send_task) => recv_task.abort(),
+/// Same checks as `auth_middleware`: a valid token for a user that still exists.
+async fn authenticate(db: &DatabaseConnection, token: &str) -> Option<Claims> {
+    let claims = verify_token(token).ok()?;
+    let user_id = Uuid::parse_str(&claims.sub).ok()?;
+    User::find_by_id(user_id).one(db).await.ok()??;
+    Some(claims)
+}
+
+fn close(code: u16, reason: &'static str) -> Message {
+    Message::Close(Some(CloseFrame { code, reason: reason.into() }))
+}
+
+/// Who opened `ticket_id`, if the typist may see the ticket: staff see every ticket and
+/// customers those they opened.
+async fn typing_requester(db: &DatabaseConnection, claims: &Claims, ticket_id: Uuid) -> Option<Uuid> {
+    let requester = Ticket::find_by_id(ticket_id)
+        .select_only()
+        .column(ticket::Column::CreatedBy)
+        .into_tuple::<Uuid>()
+        .one(db)
+        .await;
+    match requester {
+        Ok(requester) => requester.filter(|requester| claims.is_staff() || *requester == claims.user_id()),
+        Err(e) => {
+            tracing::warn!(%ticket_id, "failed to look up the ticket of a typing indicator: {e}");
+            None
+        }
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file